@@ -0,0 +1,49 @@
+use rand::Rng;
+use std::time::Duration;
+
+/// Exponential backoff with jitter for retrying failed operations, such as fetches and requests
+#[derive(Debug)]
+pub(crate) struct Backoff {
+    base: Duration,
+    max: Duration,
+    failures: u32,
+}
+
+impl Backoff {
+    pub(crate) fn new(base: Duration, max: Duration) -> Self {
+        Self {
+            base,
+            max: max.max(base),
+            failures: 0,
+        }
+    }
+
+    /// The number of failures since the last successful attempt
+    pub(crate) fn failures(&self) -> u32 {
+        self.failures
+    }
+
+    /// Whether any attempts have failed since the last success
+    pub(crate) fn is_active(&self) -> bool {
+        self.failures > 0
+    }
+
+    /// Record a failed attempt, returning how long to wait before trying again
+    ///
+    /// The delay is chosen uniformly from the upper half of the current window, but never below the
+    /// base interval, so that replicas spread out their retries without polling faster than it.
+    pub(crate) fn fail(&mut self) -> Duration {
+        self.failures = self.failures.saturating_add(1);
+
+        let factor = 2u32.saturating_pow(self.failures.min(16));
+        let ceiling = self.base.saturating_mul(factor).min(self.max);
+
+        let floor = (ceiling / 2).max(self.base);
+        rand::thread_rng().gen_range(floor..=ceiling)
+    }
+
+    /// Record a successful attempt
+    pub(crate) fn reset(&mut self) {
+        self.failures = 0;
+    }
+}
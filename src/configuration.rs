@@ -0,0 +1,159 @@
+use crate::{
+    backoff::Backoff,
+    hive::duration_from_env,
+    source::{self, Endpoint, File, Remote, Source},
+};
+use anyhow::{bail, Context, Result};
+use apollo_router::{Configuration, ConfigurationSource};
+use futures::StreamExt;
+use http::{header, HeaderMap, HeaderValue};
+use sha2::{Digest, Sha256};
+use std::{env, str::FromStr, time::Duration};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{info_span, Instrument};
+use url::Url;
+
+/// Load the router configuration from the source named by `ROUTER_CONFIG_SOURCE`
+///
+/// Returns `None` when no source is set, leaving the router to load its configuration file as
+/// usual.
+pub(crate) fn source() -> Result<Option<ConfigurationSource>> {
+    let provider: Box<dyn Source> = match env::var("ROUTER_CONFIG_SOURCE").as_deref() {
+        Err(_) => return Ok(None),
+        Ok("file") => {
+            let path = env::var_os("ROUTER_CONFIG_PATH")
+                .context("missing ROUTER_CONFIG_PATH environment variable")?;
+            Box::new(File::new(path)?)
+        }
+        Ok("http") => {
+            let url = env::var("ROUTER_CONFIG_URL")
+                .context("missing ROUTER_CONFIG_URL environment variable")?;
+            let url = Url::parse(&url).context("invalid router configuration URL")?;
+
+            let mut headers = HeaderMap::new();
+            if let Ok(token) = env::var("ROUTER_CONFIG_TOKEN") {
+                let value = HeaderValue::from_str(&format!("Bearer {token}"))
+                    .context("invalid router configuration token")?;
+                headers.insert(header::AUTHORIZATION, value);
+            }
+
+            let timeout =
+                duration_from_env("ROUTER_CONFIG_REQUEST_TIMEOUT", Duration::from_secs(30))
+                    .context("invalid request timeout format")?;
+            Box::new(Remote::new(
+                source::client(timeout)?,
+                Endpoint::new(url, headers),
+            ))
+        }
+        Ok(source) => bail!("unknown router configuration source {source:?}"),
+    };
+
+    let poll_interval = duration_from_env("ROUTER_CONFIG_POLL_INTERVAL", Duration::from_secs(30))
+        .context("invalid poll interval format")?;
+    let max_backoff = duration_from_env("ROUTER_CONFIG_MAX_BACKOFF", Duration::from_secs(300))
+        .context("invalid max backoff format")?;
+
+    let (sender, receiver) = mpsc::channel(1);
+    let watcher = Watcher {
+        provider,
+        sender,
+        backoff: Backoff::new(poll_interval, max_backoff),
+        poll_interval,
+        last_seen: None,
+    };
+    drop(tokio::task::spawn(
+        watcher.run().instrument(info_span!("configuration")),
+    ));
+
+    Ok(Some(ConfigurationSource::Stream(
+        ReceiverStream::new(receiver).boxed(),
+    )))
+}
+
+/// Watches a source for configuration changes, forwarding only those that are valid
+struct Watcher {
+    provider: Box<dyn Source>,
+    sender: mpsc::Sender<Configuration>,
+    backoff: Backoff,
+    poll_interval: Duration,
+    /// The hash of the last configuration that was forwarded to the router
+    last_seen: Option<[u8; 32]>,
+}
+
+impl Watcher {
+    async fn run(mut self) {
+        loop {
+            let fetch = self.provider.fetch().await;
+            let delay = match fetch.result {
+                Ok(contents) => {
+                    self.backoff.reset();
+
+                    if let Some(contents) = contents {
+                        if self.accept(&contents, &fetch.source).await.is_err() {
+                            tracing::debug!("router is likely shutting down");
+                            break;
+                        }
+                    }
+
+                    self.poll_interval
+                }
+                Err(err) => {
+                    let delay = self.backoff.fail();
+                    tracing::error!(
+                        code = "ROUTER_CONFIG_FETCH_FAILURE",
+                        consecutive_failures = self.backoff.failures(),
+                        retry_in = ?delay,
+                        source = %fetch.source,
+                        "{:#}",
+                        err
+                    );
+                    delay
+                }
+            };
+
+            self.provider.wait(delay).await;
+        }
+    }
+
+    /// Validate a configuration and forward it to the router if it has changed
+    ///
+    /// Invalid configurations are logged and dropped, so the router keeps its current one. They
+    /// aren't remembered, so the same contents are checked again on the next fetch.
+    async fn accept(
+        &mut self,
+        contents: &str,
+        source: &str,
+    ) -> Result<(), mpsc::error::SendError<Configuration>> {
+        let hash: [u8; 32] = Sha256::digest(contents.as_bytes()).into();
+        if self.last_seen == Some(hash) {
+            return Ok(());
+        }
+
+        let configuration = match Configuration::from_str(contents) {
+            Ok(configuration) => configuration,
+            Err(err) => {
+                tracing::info!(
+                    monotonic_counter.router_config_updates_total = 1u64,
+                    status = "rejected",
+                );
+                tracing::error!(
+                    code = "ROUTER_CONFIG_INVALID",
+                    %source,
+                    "rejected router configuration: {}",
+                    err
+                );
+                return Ok(());
+            }
+        };
+
+        tracing::info!(
+            monotonic_counter.router_config_updates_total = 1u64,
+            status = "accepted",
+        );
+        tracing::info!(hash = %hex::encode(hash), %source, "received new router configuration");
+        self.last_seen = Some(hash);
+
+        self.sender.send(configuration).await
+    }
+}
@@ -1,122 +1,78 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Result};
 use futures::{Stream, StreamExt};
-use http::{header, HeaderMap, HeaderValue, StatusCode};
-use reqwest::Client;
 use sha2::{Digest, Sha256};
-use std::{env, time::Duration};
-use tokio::sync::mpsc::channel;
+use std::sync::{Arc, OnceLock};
+use tokio::sync::mpsc;
 use tokio_stream::wrappers::ReceiverStream;
 use tracing::{info_span, Instrument};
-use url::Url;
-
-static COMMIT: Option<&'static str> = option_env!("GITHUB_SHA");
 
+mod cache;
+mod cdn;
+mod config;
+mod diff;
+mod guard;
+mod history;
+mod notifications;
+mod poller;
+mod readiness;
+mod registry;
+mod supergraph;
+mod usage;
+
+use config::RegistryConfig;
+use history::History;
+use poller::Poller;
+use registry::Shared;
+
+pub(crate) use config::{duration_from_env, Settings};
+pub(crate) use registry::{PinError, Registry};
+pub(crate) use supergraph::Supergraph;
+pub(crate) use usage::coordinates;
+
+static REGISTRY: OnceLock<Registry> = OnceLock::new();
+
+/// The most operations that can be waiting to have their field usage recorded
+const USAGE_QUEUE_SIZE: usize = 1024;
+
+/// Start polling for the supergraph, configured from the environment until the router loads the
+/// registry plugin
 pub(crate) fn schema() -> Result<impl Stream<Item = String> + Send> {
-    let config = RegistryConfig::from_env()?;
-    let (sender, receiver) = channel(2);
-
-    let headers = {
-        let mut map = HeaderMap::new();
-        map.insert(
-            header::USER_AGENT,
-            HeaderValue::from_str(&format!("apollo-router/{}", COMMIT.unwrap_or("local"))).unwrap(),
-        );
-        map.insert(
-            "X-Hive-CDN-Key",
-            HeaderValue::from_str(&config.key).unwrap(),
-        );
-        map
-    };
-    let client = Client::builder().default_headers(headers).build()?;
-
-    let task = async move {
-        let mut etag = None;
-        let mut last_schema = None;
-
-        loop {
-            let request = match etag.as_deref() {
-                Some(etag) => client
-                    .get(config.endpoint.as_str())
-                    .header(header::IF_NONE_MATCH, HeaderValue::from_str(etag).unwrap()),
-                None => client.get(config.endpoint.as_str()),
-            };
-
-            match request.send().await {
-                Ok(response) => {
-                    tracing::info!(
-                        monotonic_counter.hive_registry_fetch_count_total = 1u64,
-                        status = "success",
-                    );
-
-                    etag = response
-                        .headers()
-                        .get("etag")
-                        .and_then(|etag| etag.to_str().ok())
-                        .map(ToOwned::to_owned);
-
-                    if response.status() != StatusCode::NOT_MODIFIED {
-                        match response.text().await {
-                            Ok(schema) => {
-                                let schema_hash = Some(hash(schema.as_bytes()));
-                                if schema_hash != last_schema {
-                                    last_schema = schema_hash;
-                                    if let Err(e) = sender.send(schema).await {
-                                        tracing::debug!("failed to push to stream, router is likely shutting down: {e}");
-                                        break;
-                                    }
-                                }
-                            }
-                            Err(err) => log_fetch_failure(err),
-                        }
-                    }
-                }
-                Err(err) => log_fetch_failure(err),
-            };
-
-            tokio::time::sleep(config.poll_interval).await;
-        }
-    };
-    drop(tokio::task::spawn(task.instrument(info_span!("registry"))));
+    let config = RegistryConfig::new(Settings::default())?;
+    let (sender, receiver) = mpsc::channel(2);
+    let (commands, command_receiver) = mpsc::channel(4);
+    let (usage, usage_receiver) = mpsc::channel(USAGE_QUEUE_SIZE);
+
+    let shared = Arc::new(Shared::new(History::new(config.startup.history_size)));
+    let registry = Registry::new(shared.clone(), commands, usage, config.startup.clone());
+    let readiness = config
+        .startup
+        .readiness_listen
+        .map(|address| readiness::serve(address, registry.clone()))
+        .transpose()?;
+    let poller = Poller::new(config, sender, command_receiver, shared.clone())?;
+
+    if REGISTRY.set(registry).is_err() {
+        bail!("registry already started");
+    }
+    drop(tokio::task::spawn(
+        poller.run().instrument(info_span!("registry")),
+    ));
+    drop(tokio::task::spawn(
+        poller::report_staleness(shared.clone()).instrument(info_span!("registry")),
+    ));
+    drop(tokio::task::spawn(
+        usage::record(usage_receiver, shared).instrument(info_span!("registry")),
+    ));
+    if let Some(readiness) = readiness {
+        drop(tokio::task::spawn(readiness));
+    }
 
     Ok(ReceiverStream::new(receiver).boxed())
 }
 
-struct RegistryConfig {
-    endpoint: Url,
-    key: String,
-    poll_interval: Duration,
-}
-
-impl RegistryConfig {
-    fn from_env() -> Result<RegistryConfig> {
-        let endpoint = env::var("HIVE_CDN_ENDPOINT")
-            .context("missing HIVE_CDN_ENDPOINT environment variable")?;
-        let endpoint = Url::parse(&endpoint).context("invalid CDN endpoint")?;
-
-        let key = env::var("HIVE_CDN_KEY").context("missing HIVE_CDN_KEY environment variable")?;
-
-        let poll_interval =
-            env::var("HIVE_CDN_POLL_INTERVAL").unwrap_or_else(|_| String::from("10"));
-        let poll_interval = Duration::from_secs(
-            poll_interval
-                .parse()
-                .context("invalid poll interval format")?,
-        );
-
-        Ok(RegistryConfig {
-            endpoint,
-            key,
-            poll_interval,
-        })
-    }
-}
-
-fn log_fetch_failure(err: impl std::fmt::Display) {
-    tracing::info!(
-        monotonic_counter.hive_registry_fetch_count_total = 1u64,
-        status = "failure"
-    );
-    tracing::error!(code = "HIVE_REGISTRY_FETCH_FAILURE", "{}", err);
+/// Get a handle to the registry, if it has been started
+pub(crate) fn registry() -> Option<&'static Registry> {
+    REGISTRY.get()
 }
 
 fn hash(bytes: &[u8]) -> [u8; 32] {
@@ -0,0 +1,71 @@
+use super::hash;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{io, path::PathBuf};
+use tokio::fs;
+
+const FILE_NAME: &str = "supergraph.json";
+
+/// An on-disk copy of the last supergraph received from the registry
+#[derive(Debug)]
+pub(crate) struct Cache {
+    path: PathBuf,
+}
+
+/// A supergraph persisted to the cache
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct Entry {
+    /// The hex-encoded SHA-256 hash of the schema
+    pub hash: String,
+    /// The ETag returned by the registry
+    pub etag: Option<String>,
+    /// Where the schema was fetched from
+    #[serde(default)]
+    pub source: Option<String>,
+    /// The supergraph SDL
+    pub schema: String,
+    /// Whether the router is pinned to this schema
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+impl Cache {
+    /// Use a cache stored in the given directory
+    pub(crate) fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            path: directory.into().join(FILE_NAME),
+        }
+    }
+
+    /// Load the cached supergraph, if one exists
+    pub(crate) async fn load(&self) -> Result<Option<Entry>> {
+        let contents = match fs::read(&self.path).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).context("failed to read cached schema"),
+        };
+
+        let entry = serde_json::from_slice::<Entry>(&contents)
+            .context("failed to deserialize cached schema")?;
+        if hex::encode(hash(entry.schema.as_bytes())) != entry.hash {
+            bail!("cached schema does not match its hash");
+        }
+
+        Ok(Some(entry))
+    }
+
+    /// Atomically replace the cached supergraph
+    pub(crate) async fn store(&self, entry: &Entry) -> Result<()> {
+        let contents = serde_json::to_vec(entry).context("failed to serialize schema")?;
+
+        let temporary = self.path.with_extension("json.tmp");
+        fs::write(&temporary, contents)
+            .await
+            .context("failed to write cached schema")?;
+        fs::rename(&temporary, &self.path)
+            .await
+            .context("failed to replace cached schema")?;
+
+        Ok(())
+    }
+}
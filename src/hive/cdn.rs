@@ -0,0 +1,232 @@
+use crate::source::{Endpoint, Fetch, Source};
+use http::{HeaderMap, HeaderValue};
+use reqwest::Client;
+use url::Url;
+
+/// A Hive CDN endpoint the supergraph can be fetched from
+#[derive(Debug)]
+pub(crate) struct CdnEndpoint {
+    endpoint: Endpoint,
+    failures: u32,
+}
+
+impl CdnEndpoint {
+    pub(crate) fn new(url: Url, key: HeaderValue) -> Self {
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Hive-CDN-Key", key);
+
+        Self {
+            endpoint: Endpoint::new(url, headers),
+            failures: 0,
+        }
+    }
+}
+
+/// Polls an ordered list of Hive CDN endpoints, failing over to the next after repeated failures
+pub(crate) struct Cdn {
+    client: Client,
+    endpoints: Vec<CdnEndpoint>,
+    active: usize,
+    threshold: u32,
+}
+
+impl Cdn {
+    /// Create a new provider, with the first endpoint being the primary
+    pub(crate) fn new(client: Client, endpoints: Vec<CdnEndpoint>, threshold: u32) -> Self {
+        assert!(!endpoints.is_empty(), "at least one endpoint is required");
+
+        Self {
+            client,
+            endpoints,
+            active: 0,
+            threshold: threshold.max(1),
+        }
+    }
+
+    /// Switch to the next endpoint in the list
+    fn fail_over(&mut self) {
+        let previous = self.active;
+        self.active = (self.active + 1) % self.endpoints.len();
+
+        // Ensure the full schema is fetched from the new endpoint, duplicates are discarded by hash
+        let next = &mut self.endpoints[self.active];
+        next.endpoint.set_etag(None);
+        next.failures = 0;
+
+        tracing::warn!(
+            code = "HIVE_REGISTRY_FAILOVER",
+            from = %self.endpoints[previous].endpoint.url(),
+            to = %self.endpoints[self.active].endpoint.url(),
+            "failing over to next endpoint"
+        );
+    }
+}
+
+#[async_trait::async_trait]
+impl Source for Cdn {
+    /// Fetch the supergraph from the active endpoint
+    ///
+    /// While failed over, the primary is tried first so that it's used again once it recovers.
+    async fn fetch(&mut self) -> Fetch {
+        if self.active != 0 {
+            let primary = &mut self.endpoints[0];
+            match primary.endpoint.fetch(&self.client).await {
+                Ok(schema) => {
+                    tracing::info!(endpoint = %primary.endpoint.url(), "primary endpoint recovered");
+                    primary.failures = 0;
+                    self.active = 0;
+
+                    return Fetch {
+                        source: primary.endpoint.url().to_string(),
+                        etag: primary.endpoint.etag().map(ToOwned::to_owned),
+                        result: Ok(schema),
+                    };
+                }
+                Err(err) => {
+                    primary.failures = primary.failures.saturating_add(1);
+                    tracing::debug!(endpoint = %primary.endpoint.url(), "primary endpoint still unavailable: {err}");
+                }
+            }
+        }
+
+        let active = &mut self.endpoints[self.active];
+        let result = active.endpoint.fetch(&self.client).await;
+        let fetch = Fetch {
+            source: active.endpoint.url().to_string(),
+            etag: active.endpoint.etag().map(ToOwned::to_owned),
+            result: result.map_err(Into::into),
+        };
+
+        match &fetch.result {
+            Ok(_) => active.failures = 0,
+            Err(_) => {
+                active.failures = active.failures.saturating_add(1);
+                if active.failures >= self.threshold && self.endpoints.len() > 1 {
+                    self.fail_over();
+                }
+            }
+        }
+
+        fetch
+    }
+
+    fn restore(&mut self, source: &str, etag: Option<String>) {
+        if let Some(cdn) = self
+            .endpoints
+            .iter_mut()
+            .find(|cdn| cdn.endpoint.url().as_str() == source)
+        {
+            cdn.endpoint.set_etag(etag);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::{client, testing::Server};
+    use http::{header, StatusCode};
+    use std::time::Duration;
+
+    const SCHEMA: &str = "type Query { a: Int }";
+
+    fn cdn(servers: &[&Server], threshold: u32) -> Cdn {
+        let client = client(Duration::from_secs(5)).unwrap();
+        let endpoints = servers
+            .iter()
+            .map(|server| CdnEndpoint::new(server.url(), HeaderValue::from_static("key")))
+            .collect();
+        Cdn::new(client, endpoints, threshold)
+    }
+
+    #[tokio::test]
+    async fn sends_cdn_key() {
+        let primary = Server::start(SCHEMA).await;
+        let mut cdn = cdn(&[&primary], 1);
+
+        cdn.fetch().await.result.unwrap();
+        assert_eq!(primary.requests()[0].get("X-Hive-CDN-Key").unwrap(), "key");
+    }
+
+    #[tokio::test]
+    async fn fails_over_after_threshold() {
+        let primary = Server::start(SCHEMA).await;
+        let secondary = Server::start(SCHEMA).await;
+        let mut cdn = cdn(&[&primary, &secondary], 2);
+
+        primary.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+        for _ in 0..2 {
+            let fetch = cdn.fetch().await;
+            assert_eq!(fetch.source, primary.url().as_str());
+            assert!(fetch.result.is_err());
+        }
+        assert!(secondary.requests().is_empty());
+
+        // The primary is retried first, then the secondary serves the schema
+        let fetch = cdn.fetch().await;
+        assert_eq!(fetch.source, secondary.url().as_str());
+        assert_eq!(fetch.result.unwrap().as_deref(), Some(SCHEMA));
+        assert_eq!(primary.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn returns_to_primary_once_it_recovers() {
+        let primary = Server::start(SCHEMA).await;
+        let secondary = Server::start(SCHEMA).await;
+        let mut cdn = cdn(&[&primary, &secondary], 1);
+
+        primary.set_status(StatusCode::BAD_GATEWAY);
+        assert!(cdn.fetch().await.result.is_err());
+        assert_eq!(cdn.fetch().await.source, secondary.url().as_str());
+
+        primary.set_status(StatusCode::OK);
+        let fetch = cdn.fetch().await;
+        assert_eq!(fetch.source, primary.url().as_str());
+        assert_eq!(fetch.result.unwrap().as_deref(), Some(SCHEMA));
+
+        let requests = secondary.requests().len();
+        cdn.fetch().await.result.unwrap();
+        assert_eq!(secondary.requests().len(), requests);
+    }
+
+    #[tokio::test]
+    async fn tracks_etags_per_endpoint() {
+        let primary = Server::start(SCHEMA).await;
+        let secondary = Server::start("type Query { b: Int }").await;
+        let mut cdn = cdn(&[&primary, &secondary], 1);
+
+        cdn.fetch().await.result.unwrap();
+        let fetch = cdn.fetch().await;
+        assert_eq!(fetch.etag, Some(primary.etag()));
+        assert_eq!(fetch.result.unwrap(), None);
+
+        // Failing over fetches the full schema rather than reusing the primary's ETag
+        primary.set_status(StatusCode::SERVICE_UNAVAILABLE);
+        cdn.fetch().await.result.unwrap_err();
+        let fetch = cdn.fetch().await;
+        assert_eq!(fetch.etag, Some(secondary.etag()));
+        assert!(fetch.result.unwrap().is_some());
+        assert!(secondary.requests()[0].get(header::IF_NONE_MATCH).is_none());
+
+        let fetch = cdn.fetch().await;
+        assert_eq!(fetch.result.unwrap(), None);
+        assert_eq!(
+            secondary.requests()[1].get(header::IF_NONE_MATCH).unwrap(),
+            secondary.etag().as_str()
+        );
+    }
+
+    #[tokio::test]
+    async fn restores_etag_for_matching_endpoint() {
+        let primary = Server::start(SCHEMA).await;
+        let secondary = Server::start(SCHEMA).await;
+        let mut cdn = cdn(&[&primary, &secondary], 1);
+
+        cdn.restore(secondary.url().as_str(), Some(secondary.etag()));
+        assert!(cdn.fetch().await.result.unwrap().is_some());
+
+        let mut cdn = self::cdn(&[&primary, &secondary], 1);
+        cdn.restore(primary.url().as_str(), Some(primary.etag()));
+        assert_eq!(cdn.fetch().await.result.unwrap(), None);
+    }
+}
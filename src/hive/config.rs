@@ -0,0 +1,445 @@
+use super::{
+    cdn::{Cdn, CdnEndpoint},
+    guard::{Guard, GuardMode},
+};
+use crate::source::{self, Endpoint, File, Remote};
+use anyhow::{bail, Context, Result};
+use http::{HeaderMap, HeaderValue};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::{env, fs, net::SocketAddr, path::PathBuf, time::Duration};
+use url::Url;
+
+/// Settings for the schema registry
+///
+/// The router only loads plugins once it has a supergraph, so the registry starts from its
+/// environment variables and switches to these settings once they're loaded. Any that are omitted
+/// keep falling back to the environment.
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema, PartialEq)]
+pub(crate) struct Settings {
+    /// Where the supergraph is loaded from. Defaults to `SUPERGRAPH_SOURCE`, or the Hive CDN
+    #[serde(default)]
+    source: Option<Source>,
+
+    /// How often to check for a new supergraph. Defaults to `HIVE_CDN_POLL_INTERVAL`, or 10s
+    #[serde(default, with = "humantime_serde")]
+    #[schemars(with = "Option<String>")]
+    poll_interval: Option<Duration>,
+
+    /// The longest to wait between retries after repeated failures. Defaults to
+    /// `HIVE_CDN_MAX_BACKOFF`, or 5m
+    #[serde(default, with = "humantime_serde")]
+    #[schemars(with = "Option<String>")]
+    max_backoff: Option<Duration>,
+
+    /// A server-sent events stream that announces schema changes, each of which triggers an
+    /// immediate fetch. Polling continues as a fallback, so the poll interval can be increased.
+    /// Defaults to `SUPERGRAPH_SUBSCRIPTION_URL`
+    #[serde(default)]
+    subscription_url: Option<Url>,
+
+    /// How long a single request for the supergraph may take. Defaults to
+    /// `HIVE_CDN_REQUEST_TIMEOUT`, or 30s
+    #[serde(default, with = "humantime_serde")]
+    #[schemars(with = "Option<String>")]
+    request_timeout: Option<Duration>,
+
+    /// How long to wait for the first supergraph before exiting, where zero waits forever. Only
+    /// read from `HIVE_STARTUP_TIMEOUT` on startup, or 2m, so it must match if set
+    #[serde(default, with = "humantime_serde")]
+    #[schemars(with = "Option<String>")]
+    startup_timeout: Option<Duration>,
+
+    /// The address to report readiness on, which is served before the first supergraph loads unlike
+    /// the router's own endpoints. Only read from `HIVE_READINESS_ADDRESS` on startup, or not
+    /// served, so it must match if set
+    #[serde(default)]
+    readiness_listen: Option<SocketAddr>,
+
+    /// The number of supergraphs to keep for pinning. Only read from `HIVE_SCHEMA_HISTORY_SIZE` on
+    /// startup, or 10, so it must match if set
+    #[serde(default)]
+    history_size: Option<usize>,
+
+    /// The directory to persist the last known good supergraph to. Only read from
+    /// `HIVE_SCHEMA_CACHE_PATH` on startup, so it must match if set
+    #[serde(default)]
+    cache_path: Option<PathBuf>,
+
+    /// What to do with a supergraph that removes fields or arguments used by recent operations on
+    /// this replica. Defaults to `HIVE_BREAKING_CHANGE_GUARD`, or off
+    #[serde(default)]
+    breaking_change_guard: Option<GuardMode>,
+
+    /// How recently a field or argument must have been used for its removal to count as breaking.
+    /// Defaults to `HIVE_BREAKING_CHANGE_WINDOW`, or 24h
+    #[serde(default, with = "humantime_serde")]
+    #[schemars(with = "Option<String>")]
+    breaking_change_window: Option<Duration>,
+}
+
+/// Where the supergraph is loaded from
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum Source {
+    /// The Hive CDN, with failover between endpoints
+    Hive {
+        /// The endpoints to fetch the supergraph from, in order of preference
+        endpoints: Vec<HiveEndpoint>,
+
+        /// The number of consecutive failures before failing over to the next endpoint
+        #[serde(default = "default_failover_threshold")]
+        failover_threshold: u32,
+    },
+    /// A local file, reloaded on change
+    File {
+        /// The path to the supergraph SDL
+        path: PathBuf,
+    },
+    /// A generic HTTP URL, polled using ETags
+    Http {
+        /// The URL of the supergraph SDL
+        url: Url,
+    },
+}
+
+/// A Hive CDN endpoint and its access key
+#[derive(Clone, Debug, Deserialize, JsonSchema, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct HiveEndpoint {
+    /// The URL of the supergraph artifact
+    url: Url,
+
+    /// The CDN access key
+    #[serde(default)]
+    key: Option<String>,
+
+    /// A file containing the CDN access key, read whenever the settings are applied
+    #[serde(default)]
+    key_file: Option<PathBuf>,
+}
+
+fn default_failover_threshold() -> u32 {
+    3
+}
+
+impl Settings {
+    /// Fail if any settings that are fixed on startup differ from the ones in use
+    pub(crate) fn check_startup(&self, startup: &Startup) -> Result<()> {
+        let mut conflicts = Vec::new();
+        if let Some(timeout) = self.startup_timeout {
+            if Some(timeout).filter(|timeout| !timeout.is_zero()) != startup.startup_timeout {
+                conflicts.push("startup_timeout (HIVE_STARTUP_TIMEOUT)");
+            }
+        }
+        if self.readiness_listen.is_some() && self.readiness_listen != startup.readiness_listen {
+            conflicts.push("readiness_listen (HIVE_READINESS_ADDRESS)");
+        }
+        if self
+            .history_size
+            .is_some_and(|size| size != startup.history_size)
+        {
+            conflicts.push("history_size (HIVE_SCHEMA_HISTORY_SIZE)");
+        }
+        if self.cache_path.is_some() && self.cache_path != startup.cache_path {
+            conflicts.push("cache_path (HIVE_SCHEMA_CACHE_PATH)");
+        }
+
+        if !conflicts.is_empty() {
+            bail!(
+                "registry settings differ from the environment the registry started with: {}",
+                conflicts.join(", ")
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// The registry settings that can only be set on startup
+#[derive(Clone, Debug)]
+pub(crate) struct Startup {
+    pub startup_timeout: Option<Duration>,
+    pub readiness_listen: Option<SocketAddr>,
+    pub history_size: usize,
+    pub cache_path: Option<PathBuf>,
+}
+
+/// The registry settings, resolved against the environment
+pub(super) struct RegistryConfig {
+    pub settings: Settings,
+    pub source: SourceConfig,
+    pub poll_interval: Duration,
+    pub max_backoff: Duration,
+    pub request_timeout: Duration,
+    pub subscription_url: Option<Url>,
+    pub startup: Startup,
+    pub guard: Guard,
+}
+
+/// Where the supergraph is loaded from, with any keys loaded
+pub(super) enum SourceConfig {
+    /// The Hive CDN, with failover between endpoints
+    Hive {
+        endpoints: Vec<CdnEndpoint>,
+        failover_threshold: u32,
+    },
+    /// A local file, reloaded on change
+    File { path: PathBuf },
+    /// A generic HTTP URL, polled using ETags
+    Http { url: Url },
+}
+
+impl RegistryConfig {
+    /// Resolve the settings, falling back to the environment for anything that's missing
+    pub(super) fn new(settings: Settings) -> Result<RegistryConfig> {
+        let source = match &settings.source {
+            Some(source) => SourceConfig::from_settings(source)?,
+            None => SourceConfig::from_env()?,
+        };
+
+        let poll_interval = match settings.poll_interval {
+            Some(interval) => interval,
+            None => duration_from_env("HIVE_CDN_POLL_INTERVAL", Duration::from_secs(10))
+                .context("invalid poll interval format")?,
+        };
+        let max_backoff = match settings.max_backoff {
+            Some(max_backoff) => max_backoff,
+            None => duration_from_env("HIVE_CDN_MAX_BACKOFF", Duration::from_secs(300))
+                .context("invalid max backoff format")?,
+        };
+        let request_timeout = match settings.request_timeout {
+            Some(timeout) => timeout,
+            None => duration_from_env("HIVE_CDN_REQUEST_TIMEOUT", Duration::from_secs(30))
+                .context("invalid request timeout format")?,
+        };
+
+        let subscription_url = match &settings.subscription_url {
+            Some(url) => Some(url.clone()),
+            None => env::var("SUPERGRAPH_SUBSCRIPTION_URL")
+                .ok()
+                .map(|url| Url::parse(&url))
+                .transpose()
+                .context("invalid subscription URL")?,
+        };
+
+        // A timeout of zero waits indefinitely
+        let startup_timeout = match settings.startup_timeout {
+            Some(timeout) => timeout,
+            None => duration_from_env("HIVE_STARTUP_TIMEOUT", Duration::from_secs(120))
+                .context("invalid startup timeout format")?,
+        };
+        let startup_timeout = Some(startup_timeout).filter(|timeout| !timeout.is_zero());
+
+        let readiness_listen = match settings.readiness_listen {
+            Some(address) => Some(address),
+            None => env::var("HIVE_READINESS_ADDRESS")
+                .ok()
+                .map(|address| address.parse())
+                .transpose()
+                .context("invalid readiness address")?,
+        };
+
+        let history_size = match settings.history_size {
+            Some(size) => size,
+            None => env::var("HIVE_SCHEMA_HISTORY_SIZE")
+                .unwrap_or_else(|_| String::from("10"))
+                .parse()
+                .context("invalid schema history size format")?,
+        };
+
+        let cache_path = settings
+            .cache_path
+            .clone()
+            .or_else(|| env::var_os("HIVE_SCHEMA_CACHE_PATH").map(PathBuf::from));
+
+        let mode = match settings.breaking_change_guard {
+            Some(mode) => mode,
+            None => env::var("HIVE_BREAKING_CHANGE_GUARD")
+                .unwrap_or_else(|_| String::from("off"))
+                .parse()
+                .map_err(anyhow::Error::msg)?,
+        };
+        let window = match settings.breaking_change_window {
+            Some(window) => window,
+            None => duration_from_env("HIVE_BREAKING_CHANGE_WINDOW", Duration::from_secs(86400))
+                .context("invalid breaking change window format")?,
+        };
+
+        Ok(RegistryConfig {
+            settings,
+            source,
+            poll_interval,
+            max_backoff,
+            request_timeout,
+            subscription_url,
+            startup: Startup {
+                startup_timeout,
+                readiness_listen,
+                history_size,
+                cache_path,
+            },
+            guard: Guard { mode, window },
+        })
+    }
+}
+
+impl SourceConfig {
+    fn from_settings(source: &Source) -> Result<SourceConfig> {
+        Ok(match source {
+            Source::Hive {
+                endpoints,
+                failover_threshold,
+            } => {
+                if endpoints.is_empty() {
+                    bail!("at least one Hive CDN endpoint is required");
+                }
+
+                let endpoints = endpoints
+                    .iter()
+                    .map(|endpoint| {
+                        let key = match (&endpoint.key, &endpoint.key_file) {
+                            (Some(key), None) => key.clone(),
+                            (None, Some(path)) => fs::read_to_string(path)
+                                .with_context(|| format!("failed to read CDN key from {path:?}"))?
+                                .trim()
+                                .to_owned(),
+                            _ => bail!("exactly one of key or key_file must be set"),
+                        };
+
+                        let key = HeaderValue::from_str(&key).context("invalid CDN key")?;
+                        Ok(CdnEndpoint::new(endpoint.url.clone(), key))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+
+                SourceConfig::Hive {
+                    endpoints,
+                    failover_threshold: *failover_threshold,
+                }
+            }
+            Source::File { path } => SourceConfig::File { path: path.clone() },
+            Source::Http { url } => SourceConfig::Http { url: url.clone() },
+        })
+    }
+
+    fn from_env() -> Result<SourceConfig> {
+        Ok(match env::var("SUPERGRAPH_SOURCE").as_deref() {
+            Ok("hive") | Err(_) => SourceConfig::hive_from_env()?,
+            Ok("file") => {
+                let path = env::var_os("SUPERGRAPH_PATH")
+                    .context("missing SUPERGRAPH_PATH environment variable")?;
+                SourceConfig::File { path: path.into() }
+            }
+            Ok("http") => {
+                let url = env::var("SUPERGRAPH_URL")
+                    .context("missing SUPERGRAPH_URL environment variable")?;
+                let url = Url::parse(&url).context("invalid supergraph URL")?;
+                SourceConfig::Http { url }
+            }
+            Ok(source) => bail!("unknown supergraph source {source:?}"),
+        })
+    }
+
+    fn hive_from_env() -> Result<SourceConfig> {
+        let urls = env::var("HIVE_CDN_ENDPOINT")
+            .context("missing HIVE_CDN_ENDPOINT environment variable")?;
+        let keys = env::var("HIVE_CDN_KEY").context("missing HIVE_CDN_KEY environment variable")?;
+
+        let urls = urls.split(',').map(str::trim).collect::<Vec<_>>();
+        let keys = keys.split(',').map(str::trim).collect::<Vec<_>>();
+        if urls.len() != keys.len() {
+            bail!("HIVE_CDN_ENDPOINT and HIVE_CDN_KEY must have the same number of entries");
+        }
+
+        let endpoints = urls
+            .into_iter()
+            .zip(keys)
+            .map(|(url, key)| {
+                let url = Url::parse(url).context("invalid CDN endpoint")?;
+                let key = HeaderValue::from_str(key).context("invalid CDN key")?;
+                Ok(CdnEndpoint::new(url, key))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let failover_threshold = env::var("HIVE_CDN_FAILOVER_THRESHOLD")
+            .unwrap_or_else(|_| String::from("3"))
+            .parse()
+            .context("invalid failover threshold format")?;
+
+        Ok(SourceConfig::Hive {
+            endpoints,
+            failover_threshold,
+        })
+    }
+
+    pub(super) fn into_provider(self, timeout: Duration) -> Result<Box<dyn source::Source>> {
+        Ok(match self {
+            SourceConfig::Hive {
+                endpoints,
+                failover_threshold,
+            } => Box::new(Cdn::new(
+                source::client(timeout)?,
+                endpoints,
+                failover_threshold,
+            )),
+            SourceConfig::File { path } => Box::new(File::new(path)?),
+            SourceConfig::Http { url } => Box::new(Remote::new(
+                source::client(timeout)?,
+                Endpoint::new(url, HeaderMap::new()),
+            )),
+        })
+    }
+}
+
+/// Read a duration such as `30s` or `5m` from an environment variable
+///
+/// Bare numbers are read as seconds, as they were before units were accepted.
+pub(crate) fn duration_from_env(name: &str, default: Duration) -> Result<Duration> {
+    let Ok(value) = env::var(name) else {
+        return Ok(default);
+    };
+
+    match value.parse() {
+        Ok(seconds) => Ok(Duration::from_secs(seconds)),
+        Err(_) => Ok(humantime::parse_duration(&value)?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn startup() -> Startup {
+        Startup {
+            startup_timeout: Some(Duration::from_secs(120)),
+            readiness_listen: None,
+            history_size: 10,
+            cache_path: None,
+        }
+    }
+
+    #[test]
+    fn accepts_matching_startup_settings() {
+        let settings = Settings {
+            startup_timeout: Some(Duration::from_secs(120)),
+            history_size: Some(10),
+            ..Settings::default()
+        };
+        settings.check_startup(&startup()).unwrap();
+        Settings::default().check_startup(&startup()).unwrap();
+    }
+
+    #[test]
+    fn rejects_conflicting_startup_settings() {
+        let settings = Settings {
+            startup_timeout: Some(Duration::ZERO),
+            cache_path: Some(PathBuf::from("/tmp/cache")),
+            ..Settings::default()
+        };
+
+        let err = settings.check_startup(&startup()).unwrap_err().to_string();
+        assert!(err.contains("startup_timeout"), "{err}");
+        assert!(err.contains("cache_path"), "{err}");
+        assert!(!err.contains("history_size"), "{err}");
+    }
+}
@@ -0,0 +1,120 @@
+use super::supergraph::{Field, Supergraph};
+use std::collections::BTreeMap;
+
+/// The structural changes between two supergraphs
+#[derive(Debug, Default)]
+pub(crate) struct SchemaDiff {
+    pub types: Changes,
+    pub fields: Changes,
+    pub subgraphs: Changes,
+}
+
+/// The names of the items that were added, removed, or changed
+#[derive(Debug, Default)]
+pub(crate) struct Changes {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+impl SchemaDiff {
+    /// Compute the changes from the previous supergraph to the next
+    pub(crate) fn between(previous: &Supergraph, next: &Supergraph) -> Self {
+        Self {
+            types: Changes::between(&kinds(previous), &kinds(next)),
+            fields: Changes::between(&fields(previous), &fields(next)),
+            subgraphs: Changes::between(&previous.subgraphs, &next.subgraphs),
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.types.is_empty() && self.fields.is_empty() && self.subgraphs.is_empty()
+    }
+
+    /// Record the diff as a single structured log event and as counters
+    pub(crate) fn record(&self, previous: &str, hash: &str) {
+        tracing::info!(
+            code = "HIVE_REGISTRY_SCHEMA_CHANGED",
+            previous,
+            hash,
+            types.added = ?self.types.added,
+            types.removed = ?self.types.removed,
+            types.changed = ?self.types.changed,
+            fields.added = ?self.fields.added,
+            fields.removed = ?self.fields.removed,
+            fields.changed = ?self.fields.changed,
+            subgraphs.added = ?self.subgraphs.added,
+            subgraphs.removed = ?self.subgraphs.removed,
+            subgraphs.changed = ?self.subgraphs.changed,
+            "supergraph changed"
+        );
+
+        for (kind, changes) in [
+            ("type", &self.types),
+            ("field", &self.fields),
+            ("subgraph", &self.subgraphs),
+        ] {
+            for (change, names) in [
+                ("added", &changes.added),
+                ("removed", &changes.removed),
+                ("changed", &changes.changed),
+            ] {
+                if !names.is_empty() {
+                    tracing::info!(
+                        monotonic_counter.hive_registry_schema_changes_total = names.len() as u64,
+                        kind,
+                        change,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// The kind of each type in the supergraph
+fn kinds(supergraph: &Supergraph) -> BTreeMap<String, &'static str> {
+    supergraph
+        .types
+        .iter()
+        .map(|(name, ty)| (name.clone(), ty.kind))
+        .collect()
+}
+
+/// Every field in the supergraph, keyed by its coordinate
+fn fields(supergraph: &Supergraph) -> BTreeMap<String, &Field> {
+    supergraph
+        .types
+        .iter()
+        .flat_map(|(ty, definition)| {
+            definition
+                .fields
+                .iter()
+                .map(move |(name, field)| (format!("{ty}.{name}"), field))
+        })
+        .collect()
+}
+
+impl Changes {
+    fn between<V: PartialEq>(previous: &BTreeMap<String, V>, next: &BTreeMap<String, V>) -> Self {
+        let mut changes = Self::default();
+
+        for (name, value) in previous {
+            match next.get(name) {
+                Some(other) if other != value => changes.changed.push(name.clone()),
+                Some(_) => {}
+                None => changes.removed.push(name.clone()),
+            }
+        }
+        changes.added = next
+            .keys()
+            .filter(|name| !previous.contains_key(*name))
+            .cloned()
+            .collect();
+
+        changes
+    }
+
+    fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
@@ -0,0 +1,122 @@
+use super::supergraph::Supergraph;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
+
+/// What to do with a supergraph that removes fields or arguments used by recent operations
+#[derive(Clone, Copy, Debug, Default, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum GuardMode {
+    /// Don't check for breaking changes
+    #[default]
+    Off,
+    /// Log the breaking changes, but apply the supergraph anyway
+    Warn,
+    /// Keep using the current supergraph
+    Refuse,
+}
+
+impl FromStr for GuardMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(GuardMode::Off),
+            "warn" => Ok(GuardMode::Warn),
+            "refuse" => Ok(GuardMode::Refuse),
+            _ => Err(format!("unknown breaking change guard mode {s:?}")),
+        }
+    }
+}
+
+/// How new supergraphs are checked against recent traffic
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Guard {
+    pub mode: GuardMode,
+    /// How far back operations are considered
+    pub window: Duration,
+}
+
+/// When each field and argument was last used by an operation on this replica
+///
+/// Only coordinates that exist in a supergraph are recorded, and entries older than the window are
+/// dropped whenever a supergraph is checked, so the size is bounded by the schemas in use.
+#[derive(Debug, Default)]
+pub(crate) struct FieldUsage {
+    last_used: HashMap<String, SystemTime>,
+}
+
+impl FieldUsage {
+    /// Record the schema coordinates used by an operation
+    pub(crate) fn record(&mut self, coordinates: impl IntoIterator<Item = String>, at: SystemTime) {
+        for coordinate in coordinates {
+            match self.last_used.get_mut(&coordinate) {
+                Some(last_used) => *last_used = at,
+                None => {
+                    self.last_used.insert(coordinate, at);
+                }
+            }
+        }
+    }
+
+    /// Find the coordinates used within the window that exist in the active supergraph, but not in
+    /// the next one
+    pub(crate) fn removed(
+        &mut self,
+        window: Duration,
+        active: &Supergraph,
+        next: &Supergraph,
+    ) -> Vec<String> {
+        let now = SystemTime::now();
+        self.last_used.retain(|_, &mut last_used| {
+            now.duration_since(last_used).unwrap_or_default() <= window
+        });
+
+        let mut removed = self
+            .last_used
+            .keys()
+            .filter(|coordinate| active.contains(coordinate) && !next.contains(coordinate))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        removed.sort_unstable();
+        removed
+    }
+}
+
+/// The outcome of the most recent breaking change check that found anything
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct GuardDecision {
+    /// The hex-encoded hash of the supergraph that was checked
+    pub hash: String,
+    /// Whether the supergraph was `refused` or applied with a warning
+    pub action: &'static str,
+    /// The fields and arguments the supergraph removes that are still in use
+    pub removed: Vec<String>,
+    pub checked_at: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hive::supergraph;
+
+    #[test]
+    fn drops_usage_outside_the_window() {
+        let window = Duration::from_secs(60);
+        let now = SystemTime::now();
+        let mut usage = FieldUsage::default();
+        usage.record([String::from("User.name")], now - window * 2);
+        usage.record([String::from("User.id")], now);
+
+        let active = supergraph::example();
+        let next = supergraph::example();
+        assert!(usage.removed(window, &active, &next).is_empty());
+        assert_eq!(usage.last_used.len(), 1);
+        assert!(usage.last_used.contains_key("User.id"));
+    }
+}
@@ -0,0 +1,58 @@
+use std::{collections::VecDeque, time::SystemTime};
+
+/// A supergraph previously received from the provider
+#[derive(Clone, Debug)]
+pub(crate) struct Entry {
+    pub hash: [u8; 32],
+    pub schema: String,
+    pub source: String,
+    pub etag: Option<String>,
+    pub received_at: SystemTime,
+}
+
+/// A bounded list of recently received supergraphs, newest first
+#[derive(Debug)]
+pub(crate) struct History {
+    entries: VecDeque<Entry>,
+    capacity: usize,
+}
+
+impl History {
+    pub(crate) fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Add a supergraph to the history, evicting the oldest entry that isn't retained
+    pub(crate) fn push(&mut self, entry: Entry, retain: Option<[u8; 32]>) {
+        self.entries.retain(|existing| existing.hash != entry.hash);
+        self.entries.push_front(entry);
+
+        while self.entries.len() > self.capacity {
+            let evict = self
+                .entries
+                .iter()
+                .rposition(|existing| Some(existing.hash) != retain);
+            match evict {
+                Some(index) => drop(self.entries.remove(index)),
+                None => break,
+            }
+        }
+    }
+
+    pub(crate) fn get(&self, hash: &[u8; 32]) -> Option<&Entry> {
+        self.entries.iter().find(|entry| &entry.hash == hash)
+    }
+
+    /// The most recently received supergraph
+    pub(crate) fn latest(&self) -> Option<&Entry> {
+        self.entries.front()
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &Entry> {
+        self.entries.iter()
+    }
+}
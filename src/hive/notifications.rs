@@ -0,0 +1,109 @@
+use super::registry::Shared;
+use crate::{backoff::Backoff, source};
+use anyhow::Result;
+use http::header;
+use reqwest::Client;
+use std::{sync::Arc, time::Duration};
+use tokio::task::JoinHandle;
+use tracing::Instrument;
+use url::Url;
+
+/// Listens to a server-sent events stream, requesting a fetch whenever an event is received
+///
+/// The contents of the events are ignored, any event is treated as the schema having changed.
+pub(crate) struct Subscription {
+    url: Url,
+    task: JoinHandle<()>,
+}
+
+impl Subscription {
+    pub(crate) fn new(url: Url, connect_timeout: Duration, shared: Arc<Shared>) -> Result<Self> {
+        // The stream is long-lived, so only connecting is subject to a timeout
+        let client = source::client_builder()
+            .connect_timeout(connect_timeout)
+            .build()?;
+        let task = tokio::task::spawn(
+            run(client, url.clone(), shared).instrument(tracing::info_span!("subscription")),
+        );
+
+        Ok(Self { url, task })
+    }
+
+    pub(crate) fn url(&self) -> &Url {
+        &self.url
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Keep the subscription open, reconnecting with backoff when it drops
+async fn run(client: Client, url: Url, shared: Arc<Shared>) {
+    let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(60));
+
+    loop {
+        let result = listen(&client, &url, &shared, &mut backoff).await;
+        let delay = backoff.fail();
+
+        match result {
+            Ok(()) => tracing::info!(retry_in = ?delay, %url, "event stream closed"),
+            Err(err) => tracing::warn!(
+                code = "HIVE_REGISTRY_SUBSCRIPTION_FAILURE",
+                consecutive_failures = backoff.failures(),
+                retry_in = ?delay,
+                %url,
+                "{:#}",
+                err
+            ),
+        }
+
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Read events from the stream until it closes
+async fn listen(client: &Client, url: &Url, shared: &Shared, backoff: &mut Backoff) -> Result<()> {
+    let mut response = client
+        .get(url.as_str())
+        .header(header::ACCEPT, "text/event-stream")
+        .send()
+        .await?
+        .error_for_status()?;
+
+    tracing::info!(%url, "subscribed to schema change events");
+    backoff.reset();
+
+    // Any changes while disconnected were missed
+    shared.request_refresh("subscription");
+
+    let mut buffer = Vec::new();
+    let mut pending = false;
+    while let Some(chunk) = response.chunk().await? {
+        buffer.extend_from_slice(&chunk);
+
+        while let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+            let line = buffer.drain(..=end).collect::<Vec<_>>();
+            let line = line
+                .strip_suffix(b"\r\n")
+                .or_else(|| line.strip_suffix(b"\n"))
+                .unwrap_or(&line);
+            match line {
+                // A blank line dispatches the event
+                b"" => {
+                    if pending {
+                        shared.request_refresh("subscription");
+                        pending = false;
+                    }
+                }
+                // Comments are used as keep-alives
+                line if line.starts_with(b":") => {}
+                _ => pending = true,
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,709 @@
+use super::{
+    cache::{self, Cache},
+    config::{RegistryConfig, Settings},
+    diff::SchemaDiff,
+    guard::{Guard, GuardDecision, GuardMode},
+    hash, history,
+    notifications::Subscription,
+    registry::{Command, PinError, Shared, State},
+    supergraph::{InvalidSupergraph, Supergraph},
+};
+use crate::{
+    backoff::Backoff,
+    source::{Fetch, Source},
+};
+use anyhow::Result;
+use std::{
+    sync::{Arc, MutexGuard},
+    time::{Duration, SystemTime},
+};
+use tokio::{
+    sync::mpsc::{Receiver, Sender},
+    time::{Instant, MissedTickBehavior},
+};
+
+/// How often the staleness gauges are reported
+const STALENESS_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Periodically checks the provider for new supergraphs and forwards them to the router
+pub(crate) struct Poller {
+    provider: Box<dyn Source>,
+    sender: Sender<String>,
+    commands: Receiver<Command>,
+    shared: Arc<Shared>,
+    cache: Option<Cache>,
+    subscription: Option<Subscription>,
+    backoff: Backoff,
+    poll_interval: Duration,
+    settings: Settings,
+    guard: Guard,
+    active: Option<Active>,
+    refused: Option<Candidate>,
+}
+
+/// The supergraph currently in use by the router
+struct Active {
+    hash: [u8; 32],
+    supergraph: Supergraph,
+}
+
+/// A valid supergraph from the provider that hasn't been added to the history yet
+struct Candidate {
+    hash: [u8; 32],
+    supergraph: Supergraph,
+    schema: String,
+    source: String,
+    etag: Option<String>,
+}
+
+/// The router stopped listening for schema updates
+struct ShuttingDown;
+
+impl Poller {
+    pub(crate) fn new(
+        config: RegistryConfig,
+        sender: Sender<String>,
+        commands: Receiver<Command>,
+        shared: Arc<Shared>,
+    ) -> Result<Self> {
+        let subscription = config
+            .subscription_url
+            .map(|url| Subscription::new(url, config.request_timeout, shared.clone()))
+            .transpose()?;
+        shared.set_tracking(config.guard.mode != GuardMode::Off);
+
+        Ok(Self {
+            provider: config.source.into_provider(config.request_timeout)?,
+            sender,
+            commands,
+            shared,
+            cache: config.startup.cache_path.map(Cache::new),
+            subscription,
+            backoff: Backoff::new(config.poll_interval, config.max_backoff),
+            poll_interval: config.poll_interval,
+            settings: config.settings,
+            guard: config.guard,
+            active: None,
+            refused: None,
+        })
+    }
+
+    pub(crate) async fn run(mut self) {
+        if self.restore().await.is_err() {
+            return;
+        }
+
+        loop {
+            let started = Instant::now();
+            let fetch = self.provider.fetch().await;
+            record_fetch_metrics(&fetch, started.elapsed());
+
+            let delay = match fetch.result {
+                Ok(schema) => {
+                    tracing::info!(
+                        monotonic_counter.hive_registry_fetch_count_total = 1u64,
+                        status = "success",
+                        backoff = self.backoff.is_active(),
+                        endpoint = %fetch.source,
+                    );
+                    self.backoff.reset();
+                    {
+                        let mut state = self.state();
+                        state.last_success = Some(SystemTime::now());
+                        state.consecutive_failures = 0;
+                    }
+
+                    if let Some(schema) = schema {
+                        if self.accept(schema, fetch.source, fetch.etag).await.is_err() {
+                            break;
+                        }
+                    }
+
+                    self.poll_interval
+                }
+                Err(err) => {
+                    let delay = self.backoff.fail();
+                    {
+                        let mut state = self.state();
+                        state.last_error = Some(format!("{err:#}"));
+                        state.consecutive_failures = self.backoff.failures();
+                    }
+
+                    log_fetch_failure(err, &fetch.source, &self.backoff, delay);
+                    delay
+                }
+            };
+
+            if self.recheck_refused().await.is_err() {
+                break;
+            }
+            if self.wait(delay).await.is_err() {
+                break;
+            }
+        }
+    }
+
+    /// Wait for the provider to be ready or a fetch to be requested, handling any commands in the
+    /// meantime
+    async fn wait(&mut self, delay: Duration) -> Result<(), ShuttingDown> {
+        let deadline = Instant::now() + delay;
+
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            tokio::select! {
+                _ = self.provider.wait(remaining) => return Ok(()),
+                _ = self.shared.refresh_requested() => return Ok(()),
+                Some(command) = self.commands.recv() => self.handle(command).await?,
+            }
+        }
+    }
+
+    async fn handle(&mut self, command: Command) -> Result<(), ShuttingDown> {
+        match command {
+            Command::Pin { hash, reply } => {
+                let result = self.pin(hash).await?;
+                let _ = reply.send(result);
+            }
+            Command::Unpin { reply } => {
+                self.unpin().await?;
+                let _ = reply.send(());
+            }
+            Command::Reconfigure { settings } => self.reconfigure(*settings),
+        }
+
+        Ok(())
+    }
+
+    /// Switch to new settings, keeping the current ones if they're invalid
+    ///
+    /// The provider is replaced, so the next poll fetches the full schema, which is discarded if
+    /// it hasn't changed.
+    fn reconfigure(&mut self, settings: Settings) {
+        if settings == self.settings {
+            return;
+        }
+
+        if let Err(err) = self.apply(settings) {
+            tracing::error!(
+                code = "HIVE_REGISTRY_INVALID_CONFIG",
+                "keeping previous registry settings: {:#}",
+                err
+            );
+        }
+    }
+
+    fn apply(&mut self, settings: Settings) -> Result<()> {
+        let config = RegistryConfig::new(settings)?;
+        let provider = config.source.into_provider(config.request_timeout)?;
+
+        // Keep the existing subscription open if it hasn't changed
+        let current = self.subscription.as_ref().map(Subscription::url);
+        let subscription = match config.subscription_url {
+            Some(url) if current == Some(&url) => self.subscription.take(),
+            Some(url) => Some(Subscription::new(
+                url,
+                config.request_timeout,
+                self.shared.clone(),
+            )?),
+            None => None,
+        };
+
+        tracing::info!("applying new registry settings");
+
+        self.provider = provider;
+        self.subscription = subscription;
+        self.backoff = Backoff::new(config.poll_interval, config.max_backoff);
+        self.poll_interval = config.poll_interval;
+        self.settings = config.settings;
+        self.guard = config.guard;
+        self.shared
+            .set_tracking(config.guard.mode != GuardMode::Off);
+
+        Ok(())
+    }
+
+    /// Replay the last known good schema from the cache
+    async fn restore(&mut self) -> Result<(), ShuttingDown> {
+        let Some(cache) = &self.cache else {
+            return Ok(());
+        };
+
+        let entry = match cache.load().await {
+            Ok(Some(entry)) => entry,
+            Ok(None) => return Ok(()),
+            Err(err) => {
+                log_cache_failure(err);
+                return Ok(());
+            }
+        };
+
+        let supergraph = match Supergraph::parse(&entry.schema) {
+            Ok(supergraph) => supergraph,
+            Err(err) => {
+                log_invalid_schema(err, "cache");
+                return Ok(());
+            }
+        };
+
+        tracing::info!(
+            hash = %entry.hash,
+            source = ?entry.source,
+            pinned = entry.pinned,
+            "loaded last known good schema from cache"
+        );
+
+        if let Some(source) = &entry.source {
+            self.provider.restore(source, entry.etag.clone());
+        }
+
+        let schema_hash = hash(entry.schema.as_bytes());
+        {
+            let mut state = self.state();
+            if entry.pinned {
+                state.pinned = Some(schema_hash);
+            }
+            state.history.push(
+                history::Entry {
+                    hash: schema_hash,
+                    schema: entry.schema.clone(),
+                    source: entry.source.unwrap_or_else(|| String::from("cache")),
+                    etag: entry.etag,
+                    received_at: SystemTime::now(),
+                },
+                None,
+            );
+        }
+
+        self.activate(schema_hash, supergraph, entry.schema).await
+    }
+
+    /// Validate a schema and publish it if it has changed
+    async fn accept(
+        &mut self,
+        schema: String,
+        source: String,
+        etag: Option<String>,
+    ) -> Result<(), ShuttingDown> {
+        let schema_hash = hash(schema.as_bytes());
+        if self.refused.as_ref().map(|refused| refused.hash) == Some(schema_hash) {
+            return Ok(());
+        }
+        // Anything else the provider sends replaces a refused supergraph, including the active one
+        self.refused = None;
+        if self.state().history.latest().map(|entry| entry.hash) == Some(schema_hash) {
+            return Ok(());
+        }
+
+        let supergraph = match Supergraph::parse(&schema) {
+            Ok(supergraph) => supergraph,
+            Err(err) => {
+                log_invalid_schema(err, &source);
+                return Ok(());
+            }
+        };
+
+        let candidate = Candidate {
+            hash: schema_hash,
+            supergraph,
+            schema,
+            source,
+            etag,
+        };
+        if !self.check_breaking_changes(&candidate) {
+            self.refused = Some(candidate);
+            return Ok(());
+        }
+
+        self.admit(candidate).await
+    }
+
+    /// Check a refused supergraph again, applying it once nothing recent uses what it removes
+    ///
+    /// The provider won't send it again while it's unchanged, so it's kept until it's applied or
+    /// replaced by a newer supergraph.
+    async fn recheck_refused(&mut self) -> Result<(), ShuttingDown> {
+        let Some(candidate) = self.refused.take() else {
+            return Ok(());
+        };
+
+        if self.guard.mode == GuardMode::Refuse && !self.removed(&candidate.supergraph).is_empty() {
+            self.refused = Some(candidate);
+            return Ok(());
+        }
+
+        tracing::info!(
+            hash = %hex::encode(candidate.hash),
+            "applying previously refused supergraph"
+        );
+        self.admit(candidate).await
+    }
+
+    /// Add a supergraph to the history and publish it, unless the router is pinned
+    async fn admit(&mut self, candidate: Candidate) -> Result<(), ShuttingDown> {
+        let Candidate {
+            hash: schema_hash,
+            supergraph,
+            schema,
+            source,
+            etag,
+        } = candidate;
+
+        tracing::info!(
+            hash = %hex::encode(schema_hash),
+            endpoint = %source,
+            subgraphs = supergraph.subgraphs.len(),
+            "received new schema"
+        );
+
+        let entry = history::Entry {
+            hash: schema_hash,
+            schema,
+            source,
+            etag,
+            received_at: SystemTime::now(),
+        };
+        let pinned = {
+            let mut state = self.state();
+            let pinned = state.pinned;
+            state.history.push(entry.clone(), pinned);
+            pinned
+        };
+
+        if let Some(pinned) = pinned {
+            tracing::info!(
+                hash = %hex::encode(schema_hash),
+                pinned = %hex::encode(pinned),
+                "ignoring new schema while pinned"
+            );
+            return Ok(());
+        }
+
+        self.persist(&entry, false).await;
+        self.activate(schema_hash, supergraph, entry.schema).await
+    }
+
+    /// Check whether a supergraph removes anything recent operations used, returning whether it
+    /// can be applied
+    ///
+    /// Refused supergraphs are left out of the history and checked again on every poll, until
+    /// they're applied or replaced. Pinning bypasses the check.
+    fn check_breaking_changes(&self, candidate: &Candidate) -> bool {
+        if self.guard.mode == GuardMode::Off {
+            return true;
+        }
+
+        let removed = self.removed(&candidate.supergraph);
+        if removed.is_empty() {
+            return true;
+        }
+
+        let refused = self.guard.mode == GuardMode::Refuse;
+        let action = if refused { "refused" } else { "warned" };
+        tracing::info!(
+            monotonic_counter.hive_registry_breaking_changes_total = 1u64,
+            action,
+        );
+        if refused {
+            tracing::error!(
+                code = "HIVE_REGISTRY_BREAKING_CHANGE",
+                hash = %hex::encode(candidate.hash),
+                ?removed,
+                "refused supergraph that removes fields or arguments still in use"
+            );
+        } else {
+            tracing::warn!(
+                code = "HIVE_REGISTRY_BREAKING_CHANGE",
+                hash = %hex::encode(candidate.hash),
+                ?removed,
+                "applying supergraph that removes fields or arguments still in use"
+            );
+        }
+
+        self.state().breaking_change = Some(GuardDecision {
+            hash: hex::encode(candidate.hash),
+            action,
+            removed,
+            checked_at: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+        });
+
+        !refused
+    }
+
+    /// Find the fields and arguments used within the window that a supergraph would remove
+    fn removed(&self, supergraph: &Supergraph) -> Vec<String> {
+        match &self.active {
+            Some(active) => {
+                self.shared
+                    .usage()
+                    .removed(self.guard.window, &active.supergraph, supergraph)
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// Pin the router to a supergraph from the history
+    async fn pin(&mut self, schema_hash: [u8; 32]) -> Result<Result<(), PinError>, ShuttingDown> {
+        let Some(entry) = self.state().history.get(&schema_hash).cloned() else {
+            return Ok(Err(PinError::UnknownSchema));
+        };
+
+        tracing::warn!(hash = %hex::encode(schema_hash), "pinning schema");
+        self.state().pinned = Some(schema_hash);
+        self.persist(&entry, true).await;
+
+        self.switch_to(entry).await?;
+        Ok(Ok(()))
+    }
+
+    /// Resume using the latest supergraph from the provider
+    async fn unpin(&mut self) -> Result<(), ShuttingDown> {
+        let latest = {
+            let mut state = self.state();
+            state.pinned = None;
+            state.history.latest().cloned()
+        };
+        tracing::warn!("unpinning schema");
+
+        match latest {
+            Some(entry) => {
+                self.persist(&entry, false).await;
+                self.switch_to(entry).await
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Make a schema from the history active, if it isn't already
+    async fn switch_to(&mut self, entry: history::Entry) -> Result<(), ShuttingDown> {
+        if self.active.as_ref().map(|active| active.hash) == Some(entry.hash) {
+            return Ok(());
+        }
+
+        // Entries are validated before being added to the history
+        match Supergraph::parse(&entry.schema) {
+            Ok(supergraph) => self.activate(entry.hash, supergraph, entry.schema).await,
+            Err(err) => {
+                log_invalid_schema(err, &entry.source);
+                Ok(())
+            }
+        }
+    }
+
+    /// Record a schema as active and send it to the router
+    async fn activate(
+        &mut self,
+        schema_hash: [u8; 32],
+        supergraph: Supergraph,
+        schema: String,
+    ) -> Result<(), ShuttingDown> {
+        if let Some(active) = &self.active {
+            let diff = SchemaDiff::between(&active.supergraph, &supergraph);
+            if !diff.is_empty() {
+                diff.record(&hex::encode(active.hash), &hex::encode(schema_hash));
+            }
+        }
+
+        self.active = Some(Active {
+            hash: schema_hash,
+            supergraph,
+        });
+        {
+            let mut state = self.state();
+            state.active = Some(schema_hash);
+            state.active_since = Some(SystemTime::now());
+        }
+
+        self.sender.send(schema).await.map_err(|e| {
+            tracing::debug!("failed to push to stream, router is likely shutting down: {e}");
+            ShuttingDown
+        })?;
+        self.shared.mark_ready();
+
+        Ok(())
+    }
+
+    /// Save a schema to the cache
+    async fn persist(&mut self, entry: &history::Entry, pinned: bool) {
+        let Some(cache) = &self.cache else {
+            return;
+        };
+
+        let entry = cache::Entry {
+            hash: hex::encode(entry.hash),
+            etag: entry.etag.clone(),
+            source: Some(entry.source.clone()),
+            schema: entry.schema.clone(),
+            pinned,
+        };
+        if let Err(err) = cache.store(&entry).await {
+            log_cache_failure(err);
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.shared.state()
+    }
+}
+
+/// Report how long the active schema has been live and since the provider was last reached
+///
+/// This runs on its own fixed interval rather than in the poller, so that the gauges keep moving
+/// while the poller is waiting out a backoff or a slow fetch.
+pub(super) async fn report_staleness(shared: Arc<Shared>) {
+    let elapsed = |time: SystemTime| time.elapsed().unwrap_or_default().as_secs();
+
+    let mut interval = tokio::time::interval(STALENESS_INTERVAL);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+
+        let (active_since, last_success) = {
+            let state = shared.state();
+            (state.active_since, state.last_success)
+        };
+        if let Some(active_since) = active_since {
+            tracing::info!(value.hive_registry_schema_age_seconds = elapsed(active_since));
+        }
+        if let Some(last_success) = last_success {
+            tracing::info!(value.hive_registry_seconds_since_last_success = elapsed(last_success));
+        }
+    }
+}
+
+fn record_fetch_metrics(fetch: &Fetch, duration: Duration) {
+    let status = match &fetch.result {
+        Ok(Some(_)) => "success",
+        Ok(None) => "not_modified",
+        Err(_) => "failure",
+    };
+    tracing::info!(
+        histogram.hive_registry_fetch_duration_seconds = duration.as_secs_f64(),
+        status,
+        endpoint = %fetch.source,
+    );
+
+    match &fetch.result {
+        Ok(Some(schema)) => tracing::info!(
+            histogram.hive_registry_fetch_payload_bytes = schema.len() as u64,
+            endpoint = %fetch.source,
+        ),
+        Ok(None) => tracing::info!(
+            monotonic_counter.hive_registry_fetch_not_modified_total = 1u64,
+            endpoint = %fetch.source,
+        ),
+        Err(_) => {}
+    }
+}
+
+fn log_fetch_failure(
+    err: impl std::fmt::Display,
+    endpoint: &str,
+    backoff: &Backoff,
+    delay: Duration,
+) {
+    tracing::info!(
+        monotonic_counter.hive_registry_fetch_count_total = 1u64,
+        status = "failure",
+        backoff = backoff.is_active(),
+        endpoint,
+    );
+    tracing::error!(
+        code = "HIVE_REGISTRY_FETCH_FAILURE",
+        consecutive_failures = backoff.failures(),
+        retry_in = ?delay,
+        endpoint,
+        "{:#}",
+        err
+    );
+}
+
+fn log_invalid_schema(err: InvalidSupergraph, source: impl std::fmt::Display) {
+    tracing::info!(
+        monotonic_counter.hive_registry_schema_rejected_total = 1u64,
+        reason = err.reason(),
+    );
+    tracing::error!(
+        code = "HIVE_REGISTRY_INVALID_SCHEMA",
+        %source,
+        "rejected supergraph: {}",
+        err
+    );
+}
+
+fn log_cache_failure(err: anyhow::Error) {
+    tracing::warn!(code = "HIVE_SCHEMA_CACHE_FAILURE", "{:#}", err);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        hive::{
+            supergraph::{self, EXAMPLE},
+            usage,
+        },
+        source::{self, Endpoint, Remote},
+    };
+    use http::HeaderMap;
+
+    fn poller(guard: Guard) -> (Poller, Receiver<String>) {
+        let provider = Remote::new(
+            source::client(Duration::from_secs(1)).unwrap(),
+            Endpoint::new(
+                "http://localhost/supergraph".parse().unwrap(),
+                HeaderMap::new(),
+            ),
+        );
+        let (sender, receiver) = tokio::sync::mpsc::channel(2);
+        let (_, commands) = tokio::sync::mpsc::channel(1);
+
+        let poller = Poller {
+            provider: Box::new(provider),
+            sender,
+            commands,
+            shared: Arc::new(Shared::new(history::History::new(10))),
+            cache: None,
+            subscription: None,
+            backoff: Backoff::new(Duration::from_secs(1), Duration::from_secs(1)),
+            poll_interval: Duration::from_secs(1),
+            settings: Settings::default(),
+            guard,
+            active: None,
+            refused: None,
+        };
+        (poller, receiver)
+    }
+
+    async fn accept(poller: &mut Poller, schema: &str) {
+        let source = String::from("test");
+        let accepted = poller.accept(schema.to_owned(), source, None).await;
+        assert!(accepted.is_ok());
+    }
+
+    #[tokio::test]
+    async fn applies_refused_supergraph_once_usage_expires() {
+        let window = Duration::from_millis(200);
+        let (mut poller, mut receiver) = poller(Guard {
+            mode: GuardMode::Refuse,
+            window,
+        });
+        accept(&mut poller, EXAMPLE).await;
+        assert_eq!(receiver.recv().await.unwrap(), EXAMPLE);
+
+        let query = "{ user(id: 1) { name } }";
+        let used = usage::coordinates(&supergraph::example(), query, None).unwrap();
+        poller.shared.usage().record(used, SystemTime::now());
+
+        let next = EXAMPLE.replace("  name: String\n", "");
+        accept(&mut poller, &next).await;
+        assert!(poller.refused.is_some());
+        assert!(poller.recheck_refused().await.is_ok());
+        assert!(receiver.try_recv().is_err());
+
+        tokio::time::sleep(window * 2).await;
+        assert!(poller.recheck_refused().await.is_ok());
+        assert!(poller.refused.is_none());
+        assert_eq!(receiver.recv().await.unwrap(), next);
+        assert_eq!(poller.state().active, Some(hash(next.as_bytes())));
+    }
+}
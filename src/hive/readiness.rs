@@ -0,0 +1,87 @@
+use super::Registry;
+use anyhow::{Context, Result};
+use http::{header::CONTENT_TYPE, Method, Request, Response, StatusCode};
+use hyper::{
+    server::{conn::AddrIncoming, Server},
+    service::{make_service_fn, service_fn},
+    Body,
+};
+use std::{convert::Infallible, future::Future, net::SocketAddr};
+
+/// Bind a listener that reports whether a supergraph has been delivered to the router
+///
+/// The router only starts listening once it has a supergraph, so its own endpoints can never
+/// report that it isn't ready yet.
+pub(super) fn serve(
+    address: SocketAddr,
+    registry: Registry,
+) -> Result<impl Future<Output = ()> + Send> {
+    let incoming = AddrIncoming::bind(&address)
+        .with_context(|| format!("failed to bind readiness listener on {address}"))?;
+
+    let make_service = make_service_fn(move |_| {
+        let registry = registry.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                let response = respond(&req, registry.is_ready());
+                async move { Ok::<_, Infallible>(response) }
+            }))
+        }
+    });
+
+    let server = Server::builder(incoming).serve(make_service);
+    Ok(async move {
+        if let Err(error) = server.await {
+            tracing::error!(%error, code = "HIVE_READINESS_SERVER_FAILURE", "readiness listener failed");
+        }
+    })
+}
+
+/// Respond with the readiness of the router, in the same shape as the built-in health check
+fn respond(req: &Request<Body>, ready: bool) -> Response<Body> {
+    let builder = Response::builder();
+    let response = if req.method() != Method::GET || req.uri().path() != "/ready" {
+        builder.status(StatusCode::NOT_FOUND).body(Body::empty())
+    } else {
+        let (status, code) = match ready {
+            true => ("UP", StatusCode::OK),
+            false => ("DOWN", StatusCode::SERVICE_UNAVAILABLE),
+        };
+
+        let body = serde_json::json!({ "status": status }).to_string();
+        builder
+            .status(code)
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+    };
+
+    response.expect("response parts are valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get(path: &str) -> Request<Body> {
+        Request::get(path).body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn reports_readiness() {
+        let response = respond(&get("/ready"), false);
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(body, r#"{"status":"DOWN"}"#);
+
+        let response = respond(&get("/ready"), true);
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(body, r#"{"status":"UP"}"#);
+    }
+
+    #[test]
+    fn only_serves_readiness() {
+        let response = respond(&get("/health"), true);
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
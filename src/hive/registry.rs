@@ -0,0 +1,346 @@
+use super::{
+    config::{Settings, Startup},
+    guard::{FieldUsage, GuardDecision},
+    history::History,
+    supergraph::Supergraph,
+    usage::Operation,
+};
+use anyhow::{bail, Result};
+use serde::Serialize;
+use std::{
+    fmt::{Display, Formatter},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    time::SystemTime,
+};
+use tokio::sync::{mpsc, oneshot, watch, Notify};
+
+/// A handle for inspecting and controlling the registry poller
+#[derive(Clone)]
+pub(crate) struct Registry {
+    shared: Arc<Shared>,
+    commands: mpsc::Sender<Command>,
+    usage: mpsc::Sender<Operation>,
+    startup: Startup,
+}
+
+/// The state shared between the poller and its handles
+#[derive(Debug)]
+pub(crate) struct Shared {
+    state: Mutex<State>,
+    ready: watch::Sender<bool>,
+    refresh: Notify,
+    usage: Mutex<FieldUsage>,
+    /// Whether operations should be recorded in the field usage
+    tracking: AtomicBool,
+}
+
+impl Shared {
+    pub(crate) fn new(history: History) -> Self {
+        Self {
+            state: Mutex::new(State::new(history)),
+            ready: watch::Sender::new(false),
+            refresh: Notify::new(),
+            usage: Mutex::new(FieldUsage::default()),
+            tracking: AtomicBool::new(false),
+        }
+    }
+
+    pub(crate) fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().expect("registry state lock poisoned")
+    }
+
+    /// Record that a supergraph has been delivered to the router
+    pub(crate) fn mark_ready(&self) {
+        self.ready.send_replace(true);
+    }
+
+    /// Ask the poller to fetch the supergraph immediately
+    ///
+    /// Requests made while a fetch is in progress cause one more fetch once it completes.
+    pub(crate) fn request_refresh(&self, trigger: &'static str) {
+        tracing::info!(
+            monotonic_counter.hive_registry_notifications_total = 1u64,
+            trigger,
+        );
+        self.refresh.notify_one();
+    }
+
+    /// Wait until a fetch is requested
+    pub(crate) async fn refresh_requested(&self) {
+        self.refresh.notified().await
+    }
+
+    pub(crate) fn usage(&self) -> MutexGuard<'_, FieldUsage> {
+        self.usage.lock().expect("registry usage lock poisoned")
+    }
+
+    /// Start or stop recording the fields and arguments used by operations
+    pub(crate) fn set_tracking(&self, tracking: bool) {
+        self.tracking.store(tracking, Ordering::Relaxed);
+    }
+}
+
+/// The status of the poller and the supergraphs it has received
+#[derive(Debug)]
+pub(crate) struct State {
+    /// The hash of the supergraph currently in use by the router
+    pub active: Option<[u8; 32]>,
+    /// When the active supergraph was sent to the router
+    pub active_since: Option<SystemTime>,
+    /// The hash of the supergraph the router is pinned to
+    pub pinned: Option<[u8; 32]>,
+    pub history: History,
+    /// When the provider was last successfully contacted
+    pub last_success: Option<SystemTime>,
+    /// The most recent error from the provider
+    pub last_error: Option<String>,
+    /// The number of failed fetches since the last success
+    pub consecutive_failures: u32,
+    /// The most recent supergraph found to remove fields or arguments still in use
+    pub breaking_change: Option<GuardDecision>,
+}
+
+impl State {
+    fn new(history: History) -> Self {
+        Self {
+            active: None,
+            active_since: None,
+            pinned: None,
+            history,
+            last_success: None,
+            last_error: None,
+            consecutive_failures: 0,
+            breaking_change: None,
+        }
+    }
+}
+
+/// A request for the poller to change which supergraph is active
+pub(crate) enum Command {
+    Pin {
+        hash: [u8; 32],
+        reply: oneshot::Sender<Result<(), PinError>>,
+    },
+    Unpin {
+        reply: oneshot::Sender<()>,
+    },
+    Reconfigure {
+        settings: Box<Settings>,
+    },
+}
+
+impl Registry {
+    pub(crate) fn new(
+        shared: Arc<Shared>,
+        commands: mpsc::Sender<Command>,
+        usage: mpsc::Sender<Operation>,
+        startup: Startup,
+    ) -> Self {
+        Self {
+            shared,
+            commands,
+            usage,
+            startup,
+        }
+    }
+
+    /// The settings the registry started with, which can't be changed while it's running
+    pub(crate) fn startup(&self) -> &Startup {
+        &self.startup
+    }
+
+    /// Whether a validated supergraph has been delivered to the router
+    pub(crate) fn is_ready(&self) -> bool {
+        *self.shared.ready.borrow()
+    }
+
+    /// Wait until a validated supergraph has been delivered to the router
+    pub(crate) async fn ready(&self) {
+        let mut ready = self.shared.ready.subscribe();
+        // The sender is owned by the registry, so it can't be dropped while waiting
+        let _ = ready.wait_for(|ready| *ready).await;
+    }
+
+    /// Fail if no supergraph is delivered within the startup timeout
+    ///
+    /// Once a supergraph has been delivered, this never resolves.
+    pub(crate) async fn enforce_startup_timeout(&self) -> Result<()> {
+        if let Some(timeout) = self.startup.startup_timeout {
+            if tokio::time::timeout(timeout, self.ready()).await.is_err() {
+                bail!("no supergraph was loaded within {timeout:?} of starting");
+            }
+        }
+
+        futures::future::pending().await
+    }
+
+    /// Get the status of the poller and the active supergraph
+    pub(crate) fn status(&self) -> Status {
+        let state = self.state();
+        let active = state.active.and_then(|hash| state.history.get(&hash));
+
+        Status {
+            hash: state.active.map(hex::encode),
+            etag: active.and_then(|entry| entry.etag.clone()),
+            source: active.map(|entry| entry.source.clone()),
+            pinned: state.pinned.is_some(),
+            last_success: state.last_success.map(format_time),
+            last_error: state.last_error.clone(),
+            consecutive_failures: state.consecutive_failures,
+            breaking_change: state.breaking_change.clone(),
+        }
+    }
+
+    /// List the recently received supergraphs
+    pub(crate) fn schemas(&self) -> Schemas {
+        let state = self.state();
+
+        Schemas {
+            active: state.active.map(hex::encode),
+            pinned: state.pinned.map(hex::encode),
+            history: state
+                .history
+                .iter()
+                .map(|entry| SchemaVersion {
+                    hash: hex::encode(entry.hash),
+                    source: entry.source.clone(),
+                    received_at: format_time(entry.received_at),
+                    active: state.active == Some(entry.hash),
+                    pinned: state.pinned == Some(entry.hash),
+                })
+                .collect(),
+        }
+    }
+
+    /// Pin the router to a previously received supergraph, ignoring any new versions
+    pub(crate) async fn pin(&self, hash: &str) -> Result<(), PinError> {
+        let hash = hex::decode(hash)
+            .ok()
+            .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+            .ok_or(PinError::InvalidHash)?;
+
+        let (reply, response) = oneshot::channel();
+        self.commands
+            .send(Command::Pin { hash, reply })
+            .await
+            .map_err(|_| PinError::Unavailable)?;
+
+        response.await.map_err(|_| PinError::Unavailable)?
+    }
+
+    /// Resume following the latest supergraph from the provider
+    pub(crate) async fn unpin(&self) -> Result<(), PinError> {
+        let (reply, response) = oneshot::channel();
+        self.commands
+            .send(Command::Unpin { reply })
+            .await
+            .map_err(|_| PinError::Unavailable)?;
+
+        response.await.map_err(|_| PinError::Unavailable)
+    }
+
+    /// Fetch the supergraph immediately, such as after being notified that it changed
+    pub(crate) fn refresh(&self, trigger: &'static str) {
+        self.shared.request_refresh(trigger);
+    }
+
+    /// Apply new settings to the poller, such as after the router configuration is reloaded
+    pub(crate) async fn reconfigure(&self, settings: Settings) {
+        let command = Command::Reconfigure {
+            settings: Box::new(settings),
+        };
+        if self.commands.send(command).await.is_err() {
+            tracing::debug!("failed to reconfigure registry, poller is no longer running");
+        }
+    }
+
+    /// Whether the fields and arguments used by operations should be recorded
+    pub(crate) fn tracks_usage(&self) -> bool {
+        self.shared.tracking.load(Ordering::Relaxed)
+    }
+
+    /// Queue an operation to have the schema coordinates it uses recorded, for the breaking change
+    /// guard
+    ///
+    /// Operations are dropped while the queue is full, so requests never wait on the recording.
+    pub(crate) fn record_usage(
+        &self,
+        supergraph: &Arc<Supergraph>,
+        query: &str,
+        operation_name: Option<&str>,
+    ) {
+        let operation = Operation {
+            supergraph: supergraph.clone(),
+            query: query.to_owned(),
+            operation_name: operation_name.map(ToOwned::to_owned),
+        };
+        if self.usage.try_send(operation).is_err() {
+            tracing::info!(monotonic_counter.hive_registry_usage_dropped_total = 1u64);
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.shared.state()
+    }
+}
+
+fn format_time(time: SystemTime) -> String {
+    humantime::format_rfc3339_seconds(time).to_string()
+}
+
+/// The status of the poller and the active supergraph
+#[derive(Debug, Serialize)]
+pub(crate) struct Status {
+    hash: Option<String>,
+    etag: Option<String>,
+    source: Option<String>,
+    pinned: bool,
+    last_success: Option<String>,
+    last_error: Option<String>,
+    consecutive_failures: u32,
+    breaking_change: Option<GuardDecision>,
+}
+
+/// The supergraphs known to the registry
+#[derive(Debug, Serialize)]
+pub(crate) struct Schemas {
+    active: Option<String>,
+    pinned: Option<String>,
+    history: Vec<SchemaVersion>,
+}
+
+/// A supergraph previously received from the provider
+#[derive(Debug, Serialize)]
+pub(crate) struct SchemaVersion {
+    hash: String,
+    source: String,
+    received_at: String,
+    active: bool,
+    pinned: bool,
+}
+
+/// Why a supergraph could not be pinned
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum PinError {
+    /// The hash was not a hex-encoded SHA-256 digest
+    InvalidHash,
+    /// The supergraph is not in the history
+    UnknownSchema,
+    /// The poller is no longer running
+    Unavailable,
+}
+
+impl std::error::Error for PinError {}
+
+impl Display for PinError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidHash => write!(f, "schema hash must be a hex-encoded SHA-256 digest"),
+            Self::UnknownSchema => write!(f, "schema not found in history"),
+            Self::Unavailable => write!(f, "registry is not running"),
+        }
+    }
+}
@@ -0,0 +1,317 @@
+use graphql_parser::schema::{self, Definition, Document, InputValue, TypeDefinition, Value};
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Formatter},
+};
+
+const JOIN_GRAPH_ENUM: &str = "join__Graph";
+const JOIN_GRAPH_DIRECTIVE: &str = "join__graph";
+const REQUIRED_DIRECTIVES: [&str; 3] = [JOIN_GRAPH_DIRECTIVE, "join__type", "join__field"];
+const INTERNAL_PREFIXES: [&str; 3] = ["join__", "link__", "core__"];
+
+/// A supergraph that has been checked before being handed to the router
+#[derive(Debug)]
+pub(crate) struct Supergraph {
+    /// The subgraphs that make up the supergraph, mapped to their URLs
+    pub subgraphs: BTreeMap<String, String>,
+    /// The types exposed by the supergraph, excluding federation internals
+    pub types: BTreeMap<String, Type>,
+    /// The root types, keyed by the kind of operation they serve
+    pub roots: BTreeMap<&'static str, String>,
+}
+
+/// A summary of a type in the supergraph
+#[derive(Debug, PartialEq)]
+pub(crate) struct Type {
+    pub kind: &'static str,
+    /// The fields, input fields, enum values, or union members of the type
+    pub fields: BTreeMap<String, Field>,
+}
+
+/// A summary of a member of a type
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Field {
+    /// The type of the field, if it has one
+    pub ty: String,
+    /// The field's arguments mapped to their types
+    pub arguments: BTreeMap<String, String>,
+}
+
+impl Supergraph {
+    /// Parse and validate a supergraph SDL
+    pub(crate) fn parse(sdl: &str) -> Result<Self, InvalidSupergraph> {
+        let document = schema::parse_schema::<String>(sdl)
+            .map_err(|e| InvalidSupergraph::Syntax(e.to_string()))?;
+
+        if let Some(missing) = REQUIRED_DIRECTIVES
+            .into_iter()
+            .find(|&name| !defines_directive(&document, name))
+        {
+            return Err(InvalidSupergraph::MissingJoinDirective(missing));
+        }
+
+        let subgraphs = subgraphs(&document)?;
+        if subgraphs.is_empty() {
+            return Err(InvalidSupergraph::NoSubgraphs);
+        }
+
+        Ok(Self {
+            subgraphs,
+            types: types(&document),
+            roots: roots(&document),
+        })
+    }
+
+    /// Whether the supergraph defines a schema coordinate, such as `Type.field` or
+    /// `Type.field(argument:)`
+    pub(crate) fn contains(&self, coordinate: &str) -> bool {
+        let Some((type_name, member)) = coordinate.split_once('.') else {
+            return self.types.contains_key(coordinate);
+        };
+        let (field_name, argument) = match member.split_once('(') {
+            Some((field, argument)) => (field, Some(argument.trim_end_matches(":)"))),
+            None => (member, None),
+        };
+
+        let field = self
+            .types
+            .get(type_name)
+            .and_then(|ty| ty.fields.get(field_name));
+        match (field, argument) {
+            (Some(field), Some(argument)) => field.arguments.contains_key(argument),
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+}
+
+fn defines_directive(document: &Document<'_, String>, name: &str) -> bool {
+    document.definitions.iter().any(|definition| {
+        matches!(definition, Definition::DirectiveDefinition(directive) if directive.name == name)
+    })
+}
+
+/// Extract the subgraphs from the `join__Graph` enum
+fn subgraphs(
+    document: &Document<'_, String>,
+) -> Result<BTreeMap<String, String>, InvalidSupergraph> {
+    let graphs = document
+        .definitions
+        .iter()
+        .find_map(|definition| match definition {
+            Definition::TypeDefinition(TypeDefinition::Enum(e)) if e.name == JOIN_GRAPH_ENUM => {
+                Some(e)
+            }
+            _ => None,
+        });
+    let Some(graphs) = graphs else {
+        return Err(InvalidSupergraph::NoSubgraphs);
+    };
+
+    let mut subgraphs = BTreeMap::new();
+    for value in &graphs.values {
+        let directive = value
+            .directives
+            .iter()
+            .find(|d| d.name == JOIN_GRAPH_DIRECTIVE)
+            .ok_or_else(|| InvalidSupergraph::InvalidSubgraph(value.name.clone()))?;
+
+        let argument = |name: &str| {
+            directive
+                .arguments
+                .iter()
+                .find_map(|(key, value)| match value {
+                    Value::String(s) if key == name => Some(s.clone()),
+                    _ => None,
+                })
+        };
+        let name = argument("name")
+            .ok_or_else(|| InvalidSupergraph::InvalidSubgraph(value.name.clone()))?;
+        let url = argument("url").unwrap_or_default();
+
+        subgraphs.insert(name, url);
+    }
+
+    Ok(subgraphs)
+}
+
+/// Summarize the types in the supergraph
+fn types(document: &Document<'_, String>) -> BTreeMap<String, Type> {
+    document
+        .definitions
+        .iter()
+        .filter_map(|definition| match definition {
+            Definition::TypeDefinition(definition) => Some(summarize(definition)),
+            _ => None,
+        })
+        .filter(|(name, _)| {
+            !INTERNAL_PREFIXES
+                .iter()
+                .any(|prefix| name.starts_with(prefix))
+        })
+        .collect()
+}
+
+/// Find the root operation types, which can be renamed in the schema definition
+fn roots(document: &Document<'_, String>) -> BTreeMap<&'static str, String> {
+    let definition = document
+        .definitions
+        .iter()
+        .find_map(|definition| match definition {
+            Definition::SchemaDefinition(schema) => Some(schema),
+            _ => None,
+        });
+
+    let mut roots = BTreeMap::new();
+    let operations = [
+        ("query", definition.and_then(|d| d.query.as_ref()), "Query"),
+        (
+            "mutation",
+            definition.and_then(|d| d.mutation.as_ref()),
+            "Mutation",
+        ),
+        (
+            "subscription",
+            definition.and_then(|d| d.subscription.as_ref()),
+            "Subscription",
+        ),
+    ];
+    for (operation, name, default) in operations {
+        // The default names are only used when there is no schema definition
+        let name = match (name, definition) {
+            (Some(name), _) => name.clone(),
+            (None, None) => String::from(default),
+            (None, Some(_)) => continue,
+        };
+        roots.insert(operation, name);
+    }
+
+    roots
+}
+
+fn summarize(definition: &TypeDefinition<'_, String>) -> (String, Type) {
+    let named = |name: &String| (name.clone(), Field::default());
+    let (name, kind, fields) = match definition {
+        TypeDefinition::Scalar(t) => (&t.name, "scalar", BTreeMap::new()),
+        TypeDefinition::Object(t) => (&t.name, "object", object_fields(&t.fields)),
+        TypeDefinition::Interface(t) => (&t.name, "interface", object_fields(&t.fields)),
+        TypeDefinition::Union(t) => (&t.name, "union", t.types.iter().map(named).collect()),
+        TypeDefinition::Enum(t) => (
+            &t.name,
+            "enum",
+            t.values.iter().map(|v| named(&v.name)).collect(),
+        ),
+        TypeDefinition::InputObject(t) => (
+            &t.name,
+            "input",
+            t.fields
+                .iter()
+                .map(|f| {
+                    let field = Field {
+                        ty: input_type(f),
+                        arguments: BTreeMap::new(),
+                    };
+                    (f.name.clone(), field)
+                })
+                .collect(),
+        ),
+    };
+
+    (name.clone(), Type { kind, fields })
+}
+
+fn object_fields(fields: &[schema::Field<'_, String>]) -> BTreeMap<String, Field> {
+    fields
+        .iter()
+        .map(|f| {
+            let field = Field {
+                ty: f.field_type.to_string(),
+                arguments: f
+                    .arguments
+                    .iter()
+                    .map(|arg| (arg.name.clone(), input_type(arg)))
+                    .collect(),
+            };
+            (f.name.clone(), field)
+        })
+        .collect()
+}
+
+fn input_type(value: &InputValue<'_, String>) -> String {
+    match &value.default_value {
+        Some(default) => format!("{} = {}", value.value_type, default),
+        None => value.value_type.to_string(),
+    }
+}
+
+/// Why a supergraph was rejected
+#[derive(Debug)]
+pub(crate) enum InvalidSupergraph {
+    /// The schema could not be parsed as SDL
+    Syntax(String),
+    /// A federation join directive was not defined
+    MissingJoinDirective(&'static str),
+    /// A `join__Graph` value was missing its `@join__graph` directive
+    InvalidSubgraph(String),
+    /// The supergraph did not contain any subgraphs
+    NoSubgraphs,
+}
+
+impl InvalidSupergraph {
+    /// A short, low-cardinality identifier for the error
+    pub(crate) fn reason(&self) -> &'static str {
+        match self {
+            Self::Syntax(_) => "syntax",
+            Self::MissingJoinDirective(_) => "missing_join_directive",
+            Self::InvalidSubgraph(_) => "invalid_subgraph",
+            Self::NoSubgraphs => "no_subgraphs",
+        }
+    }
+}
+
+impl std::error::Error for InvalidSupergraph {}
+
+impl Display for InvalidSupergraph {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Syntax(e) => write!(f, "invalid SDL: {e}"),
+            Self::MissingJoinDirective(name) => {
+                write!(f, "missing federation directive @{name}")
+            }
+            Self::InvalidSubgraph(value) => {
+                write!(
+                    f,
+                    "subgraph {value} is missing a valid @{JOIN_GRAPH_DIRECTIVE}"
+                )
+            }
+            Self::NoSubgraphs => write!(f, "supergraph does not contain any subgraphs"),
+        }
+    }
+}
+
+/// A small supergraph with a `Query.user(id:)` field returning a `User` with an `id` and `name`
+#[cfg(test)]
+pub(super) const EXAMPLE: &str = r#"
+directive @join__graph(name: String!, url: String!) on ENUM_VALUE
+directive @join__type(graph: join__Graph!) repeatable on OBJECT
+directive @join__field(graph: join__Graph) on FIELD_DEFINITION
+
+enum join__Graph {
+  USERS @join__graph(name: "users", url: "http://users")
+}
+
+type Query {
+  user(id: ID!): User
+}
+
+type User {
+  id: ID!
+  name: String
+}
+"#;
+
+/// The [`EXAMPLE`] supergraph, parsed
+#[cfg(test)]
+pub(super) fn example() -> Supergraph {
+    Supergraph::parse(EXAMPLE).unwrap()
+}
@@ -0,0 +1,177 @@
+use super::{registry::Shared, supergraph::Supergraph};
+use graphql_parser::query::{
+    self, Definition, FragmentDefinition, OperationDefinition, Selection, SelectionSet,
+    TypeCondition,
+};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    sync::Arc,
+    time::SystemTime,
+};
+use tokio::sync::mpsc;
+
+/// An operation waiting to have the fields and arguments it uses recorded
+pub(crate) struct Operation {
+    pub supergraph: Arc<Supergraph>,
+    pub query: String,
+    pub operation_name: Option<String>,
+}
+
+/// Record the fields and arguments used by queued operations, keeping the parsing and locking off
+/// the request path
+pub(super) async fn record(mut operations: mpsc::Receiver<Operation>, shared: Arc<Shared>) {
+    while let Some(operation) = operations.recv().await {
+        let query = operation.query.as_str();
+        let operation_name = operation.operation_name.as_deref();
+        if let Ok(used) = coordinates(&operation.supergraph, query, operation_name) {
+            shared.usage().record(used, SystemTime::now());
+        }
+    }
+}
+
+/// Collect the schema coordinates used by an operation
+///
+/// Fields are recorded as `Type.field` and arguments as `Type.field(argument:)`. Introspection
+/// fields are skipped, as are any fields and arguments the supergraph doesn't define.
+pub(crate) fn coordinates(
+    supergraph: &Supergraph,
+    query: &str,
+    operation_name: Option<&str>,
+) -> Result<BTreeSet<String>, query::ParseError> {
+    let document = query::parse_query::<&str>(query)?;
+
+    let mut fragments = HashMap::new();
+    let mut operations = Vec::new();
+    for definition in &document.definitions {
+        match definition {
+            Definition::Fragment(fragment) => {
+                fragments.insert(fragment.name, fragment);
+            }
+            Definition::Operation(operation) => operations.push(operation),
+        }
+    }
+
+    let operation = match operation_name {
+        Some(name) => operations
+            .into_iter()
+            .find(|operation| self::operation_name(operation) == Some(name)),
+        None if operations.len() == 1 => operations.pop(),
+        None => None,
+    };
+
+    let mut collector = Collector {
+        supergraph,
+        fragments,
+        visited: HashSet::new(),
+        used: BTreeSet::new(),
+    };
+    if let Some(operation) = operation {
+        let (kind, selection_set) = match operation {
+            OperationDefinition::SelectionSet(set) => ("query", set),
+            OperationDefinition::Query(q) => ("query", &q.selection_set),
+            OperationDefinition::Mutation(m) => ("mutation", &m.selection_set),
+            OperationDefinition::Subscription(s) => ("subscription", &s.selection_set),
+        };
+
+        if let Some(root) = supergraph.roots.get(kind) {
+            collector.selection_set(root, selection_set);
+        }
+    }
+
+    Ok(collector.used)
+}
+
+fn operation_name<'a>(operation: &OperationDefinition<'a, &'a str>) -> Option<&'a str> {
+    match operation {
+        OperationDefinition::SelectionSet(_) => None,
+        OperationDefinition::Query(q) => q.name,
+        OperationDefinition::Mutation(m) => m.name,
+        OperationDefinition::Subscription(s) => s.name,
+    }
+}
+
+struct Collector<'a, 'd> {
+    supergraph: &'a Supergraph,
+    fragments: HashMap<&'d str, &'d FragmentDefinition<'d, &'d str>>,
+    /// The fragments that have already been collected, to avoid cycles
+    visited: HashSet<&'d str>,
+    used: BTreeSet<String>,
+}
+
+impl<'a, 'd> Collector<'a, 'd> {
+    fn selection_set(&mut self, type_name: &str, set: &SelectionSet<'d, &'d str>) {
+        for selection in &set.items {
+            match selection {
+                Selection::Field(field) => {
+                    if field.name.starts_with("__") {
+                        continue;
+                    }
+
+                    self.insert(format!("{type_name}.{}", field.name));
+                    for (argument, _) in &field.arguments {
+                        self.insert(format!("{type_name}.{}({argument}:)", field.name));
+                    }
+
+                    let ty = self
+                        .supergraph
+                        .types
+                        .get(type_name)
+                        .and_then(|ty| ty.fields.get(field.name))
+                        .map(|field| named_type(&field.ty).to_owned());
+                    if let Some(ty) = ty {
+                        self.selection_set(&ty, &field.selection_set);
+                    }
+                }
+                Selection::FragmentSpread(spread) => {
+                    let Some(fragment) = self.fragments.get(spread.fragment_name).copied() else {
+                        continue;
+                    };
+
+                    if self.visited.insert(fragment.name) {
+                        let TypeCondition::On(on) = fragment.type_condition;
+                        self.selection_set(on, &fragment.selection_set);
+                    }
+                }
+                Selection::InlineFragment(fragment) => {
+                    let on = match &fragment.type_condition {
+                        Some(TypeCondition::On(on)) => on,
+                        None => type_name,
+                    };
+                    self.selection_set(on, &fragment.selection_set);
+                }
+            }
+        }
+    }
+
+    /// Record a coordinate, as long as it's one the supergraph defines
+    ///
+    /// Operations haven't been validated yet, so anything else could be made up by the client.
+    fn insert(&mut self, coordinate: String) {
+        if self.supergraph.contains(&coordinate) {
+            self.used.insert(coordinate);
+        }
+    }
+}
+
+/// Strip any list and non-null wrappers from a type
+fn named_type(ty: &str) -> &str {
+    ty.trim_matches(|c| matches!(c, '[' | ']' | '!'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hive::supergraph;
+
+    #[test]
+    fn only_collects_defined_coordinates() {
+        let supergraph = supergraph::example();
+        let query = "{ user(id: 1, made_up: 2) { name made_up { id } } made_up __typename }";
+
+        let used = coordinates(&supergraph, query, None).unwrap();
+        assert_eq!(
+            used.into_iter().collect::<Vec<_>>(),
+            ["Query.user", "Query.user(id:)", "User.name"]
+        );
+    }
+}
@@ -4,31 +4,62 @@
 //! Source: https://github.com/apollographql/router/blob/da64c28/apollo-router/src/services/http/service.rs
 
 use apollo_router::{services::router, Context};
-use async_compression::tokio::write::{BrotliEncoder, GzipEncoder, ZlibEncoder};
+use async_compression::tokio::bufread::{BrotliEncoder, GzipEncoder, ZlibEncoder, ZstdEncoder};
 use futures::{
     future::{BoxFuture, TryFutureExt},
-    Stream,
+    Stream, TryStreamExt,
 };
-use http::header::{HeaderMap, HeaderValue, ACCEPT_ENCODING, CONTENT_ENCODING};
-use hyper::{body::Bytes, client::HttpConnector};
-use hyper_rustls::{ConfigBuilderExt, HttpsConnector};
+use http::{
+    header::{HeaderMap, HeaderValue, ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH},
+    StatusCode, Uri,
+};
+use hyper::{
+    body::{Bytes, HttpBody},
+    client::HttpConnector,
+};
+use hyper_rustls::HttpsConnector;
 use opentelemetry_api::global::get_text_map_propagator;
 use pin_project_lite::pin_project;
-use std::{io, task::Poll, time::Duration};
-use tokio::io::AsyncWriteExt;
+use std::{
+    io,
+    sync::{atomic::Ordering, Arc},
+    task::Poll,
+    time::{Duration, Instant},
+};
+use tokio_util::io::{ReaderStream, StreamReader};
 use tower::{BoxError, Service, ServiceBuilder};
 use tower_http::decompression::{Decompression, DecompressionBody, DecompressionLayer};
 use tracing::Instrument;
 use tracing_opentelemetry::OpenTelemetrySpanExt;
 
+mod circuit;
+mod compression;
+mod config;
+mod limit;
+mod logging;
+mod metrics;
 mod resolver;
+mod retry;
+mod tls;
+mod unix;
 
+pub use circuit::{CircuitBreakerConfig, UpstreamUnavailable};
+pub use compression::{CompressionConfig, Encoding};
+pub use config::{ClientConfig, HttpVersion};
 pub use hyper::Body;
-
-static ACCEPTED_ENCODINGS: HeaderValue = HeaderValue::from_static("gzip, br, deflate");
-
-type HttpClient =
-    Decompression<hyper::Client<HttpsConnector<HttpConnector<resolver::AsyncResolver>>, Body>>;
+pub use limit::PayloadTooLarge;
+pub use logging::LoggingConfig;
+pub use retry::RetryConfig;
+pub use tls::TlsConfig;
+
+type HttpClient = Decompression<
+    hyper::Client<
+        metrics::CountConnections<
+            unix::UnixConnector<HttpsConnector<HttpConnector<resolver::AsyncResolver>>>,
+        >,
+        Body,
+    >,
+>;
 
 pub struct Request {
     pub context: Context,
@@ -83,38 +114,86 @@ impl From<Response> for router::Response {
 #[derive(Clone)]
 pub struct Client {
     client: HttpClient,
+    request_timeout: Duration,
+    compression: Arc<CompressionConfig>,
+    accept_encoding: HeaderValue,
+    max_request_body_size: Option<u64>,
+    redactor: Arc<logging::Redactor>,
+    sockets: unix::Sockets,
+    retry: Option<Arc<retry::RetryPolicy>>,
+    circuit_breaker: Option<Arc<circuit::CircuitBreaker>>,
+    /// Reloads the TLS certificates when their files change
+    _certificates: Option<Arc<notify::RecommendedWatcher>>,
 }
 
 impl Client {
-    pub fn new() -> Result<Self, io::Error> {
+    pub fn new(config: &ClientConfig) -> Result<Self, io::Error> {
         let resolver = resolver::AsyncResolver::new()?;
         let mut http_connector = HttpConnector::new_with_resolver(resolver);
         http_connector.set_nodelay(true);
-        http_connector.set_keepalive(Some(Duration::from_secs(60)));
+        http_connector.set_keepalive(config.tcp_keepalive);
+        http_connector.set_connect_timeout(Some(config.connect_timeout));
         http_connector.enforce_http(false);
 
-        let tls_config = rustls::ClientConfig::builder()
-            .with_safe_defaults()
-            .with_native_roots()
-            .with_no_client_auth();
+        let (tls_config, certificates) = tls::client_config(&config.tls)?;
 
-        let https_connector = hyper_rustls::HttpsConnectorBuilder::new()
+        let mut https_connector = hyper_rustls::HttpsConnectorBuilder::new()
             .with_tls_config(tls_config)
-            .https_or_http()
-            .enable_http1()
-            .enable_http2()
-            .wrap_connector(http_connector);
-
-        let client = hyper::Client::builder()
-            .http2_only(true)
-            .pool_idle_timeout(Some(Duration::from_secs(5)))
-            .build(https_connector);
+            .https_or_http();
+        if let Some(server_name) = &config.tls.server_name {
+            https_connector = https_connector.with_server_name(server_name.clone());
+        }
+        let http_version = config.http_version();
+        let https_connector = match http_version {
+            HttpVersion::H1 => https_connector
+                .enable_http1()
+                .wrap_connector(http_connector),
+            HttpVersion::H2 | HttpVersion::Auto => https_connector
+                .enable_http1()
+                .enable_http2()
+                .wrap_connector(http_connector),
+        };
+
+        let mut builder = hyper::Client::builder();
+        builder
+            .http2_only(http_version == HttpVersion::H2)
+            .http2_keep_alive_interval(config.http2_keepalive)
+            .pool_idle_timeout(Some(config.pool_idle_timeout));
+        if let Some(max_idle) = config.pool_max_idle_per_host {
+            builder.pool_max_idle_per_host(max_idle);
+        }
+        let sockets = unix::Sockets::new(config.unix_socket.as_deref(), &config.unix_sockets);
+        let connector = unix::UnixConnector::new(https_connector, sockets.clone());
+        let client = builder.build(metrics::CountConnections::new(connector));
 
         let client = ServiceBuilder::new()
-            .layer(DecompressionLayer::new())
+            .layer(
+                DecompressionLayer::new()
+                    .zstd(config.compression.accepts(Encoding::Zstd))
+                    .br(config.compression.accepts(Encoding::Br))
+                    .gzip(config.compression.accepts(Encoding::Gzip))
+                    .deflate(config.compression.accepts(Encoding::Deflate)),
+            )
             .service(client);
 
-        Ok(Self { client })
+        Ok(Self {
+            client,
+            request_timeout: config.request_timeout,
+            compression: Arc::new(config.compression.clone()),
+            accept_encoding: config.compression.accept_encoding(),
+            max_request_body_size: config.max_request_body_size,
+            redactor: Arc::new(logging::Redactor::new(&config.logging)?),
+            sockets,
+            retry: config
+                .retry
+                .clone()
+                .map(|config| Arc::new(retry::RetryPolicy::new(config))),
+            circuit_breaker: config
+                .circuit_breaker
+                .clone()
+                .map(|config| Arc::new(circuit::CircuitBreaker::new(config))),
+            _certificates: certificates.map(Arc::new),
+        })
     }
 }
 
@@ -137,15 +216,13 @@ impl Service<Request> for Client {
 
         let uri = request.uri();
         let path = uri.path();
-        let host = uri.host().unwrap_or_default();
-        let port = uri.port_u16().unwrap_or_else(|| {
-            let scheme = uri.scheme_str();
-            match scheme {
-                Some("https") => 443,
-                Some("http") => 80,
-                _ => 0,
-            }
-        });
+        let (host, port) = host_and_port(uri);
+        let upstream = format!("{host}:{port}");
+
+        let transport = match self.sockets.get(&upstream) {
+            Some(_) => "unix",
+            None => "ip_tcp",
+        };
 
         let request_span = tracing::info_span!(
             "http_request",
@@ -154,7 +231,7 @@ impl Service<Request> for Client {
             net.peer.port = %port,
             http.route = %path,
             http.url = %uri,
-            net.transport = "ip_tcp",
+            net.transport = transport,
         );
         get_text_map_propagator(|propagator| {
             let mut injector = opentelemetry_http::HeaderInjector(request.headers_mut());
@@ -162,40 +239,118 @@ impl Service<Request> for Client {
         });
 
         let client = self.client.clone();
+        let request_timeout = self.request_timeout;
+        let compression = self.compression.clone();
+        let accept_encoding = self.accept_encoding.clone();
+        let max_request_body_size = self.max_request_body_size;
+        let redactor = self.redactor.clone();
+        let retry = self.retry.clone();
+        let circuit_breaker = self.circuit_breaker.clone();
         Box::pin(async move {
-            let (parts, body) = request.into_parts();
-            let body = hyper::body::to_bytes(body).await.map_err(|err| {
-                tracing::error!(compress_error = debug(&err));
-                err
-            })?;
-            let body = compress(body, &parts.headers)
-                .instrument(tracing::debug_span!("body_compression"))
-                .await
-                .map_err(|err| {
-                    tracing::error!(compress_error = debug(&err));
-                    err
-                })?;
-            let mut request = http::Request::from_parts(parts, Body::from(body));
-
-            request
-                .headers_mut()
-                .insert(ACCEPT_ENCODING, ACCEPTED_ENCODINGS.clone());
+            let (mut parts, body) = request.into_parts();
+
+            let retries = retry
+                .as_deref()
+                .and_then(|policy| policy.start(&parts.method));
+            let (body, retries) = match retries {
+                Some(retries) => {
+                    let (body, replay) = buffer(body, retries.max_buffered_body_size()).await?;
+                    (body, replay.map(|replay| (retries, replay)))
+                }
+                None => (body, None),
+            };
+
+            let (body, exceeded) = match max_request_body_size {
+                Some(limit) if limit::exceeds(&parts.headers, limit) => {
+                    return Err(PayloadTooLarge { limit }.into());
+                }
+                Some(limit)
+                    if retries
+                        .as_ref()
+                        .is_some_and(|(_, replay)| replay.len() as u64 > limit) =>
+                {
+                    return Err(PayloadTooLarge { limit }.into());
+                }
+                Some(limit) if !body.is_end_stream() => {
+                    let (body, exceeded) = limit::limit(body, limit);
+                    (body, Some((limit, exceeded)))
+                }
+                _ => (body, None),
+            };
+
+            parts.headers.insert(ACCEPT_ENCODING, accept_encoding);
+            // Checked up front so that a request that can't be encoded never reaches the breaker
+            let encoding = content_encoding(&parts.headers)?;
 
             let display_headers =
                 context.contains_key("apollo_telemetry::logging::display_headers");
             if display_headers {
-                tracing::info!(http.request.headers = ?request.headers());
+                tracing::info!(http.request.headers = ?redactor.headers(&parts.headers));
             }
-            if context.contains_key("apollo_telemetry::logging::display_body") {
-                tracing::info!(http.request.body = ?request.body());
+            let display_body = context.contains_key("apollo_telemetry::logging::display_body");
+            // Empty bodies have nothing worth logging
+            let body = if display_body && !body.is_end_stream() {
+                let span = request_span.clone();
+                logging::log_body(
+                    body,
+                    redactor.clone(),
+                    move |body| tracing::info!(parent: &span, http.request.body = %body),
+                )
+            } else {
+                body
+            };
+
+            let permit = circuit_breaker
+                .map(|breaker| breaker.acquire(&upstream))
+                .transpose()?;
+
+            let method = parts.method.clone();
+            let in_flight = metrics::InFlight::start(&upstream);
+            let started = Instant::now();
+
+            let response = send(
+                client,
+                &context,
+                parts,
+                body,
+                encoding.map(|encoding| (encoding, &*compression)),
+                request_timeout,
+                retries,
+            )
+            .instrument(request_span.clone())
+            .await;
+
+            // The client only reports that the body failed, so check whether it was the limit
+            let response = match (response, exceeded) {
+                (Err(_), Some((limit, exceeded))) if exceeded.load(Ordering::Relaxed) => {
+                    Err(PayloadTooLarge { limit }.into())
+                }
+                (response, _) => response,
+            };
+
+            metrics::record(&upstream, &method, &response, started.elapsed());
+            drop(in_flight);
+
+            if let Some(permit) = permit {
+                let success = match &response {
+                    Ok(response) => !is_unavailable(response.status()),
+                    Err(err) => !is_upstream_failure(err),
+                };
+                permit.record(success);
             }
-
-            let response = fetch(client, &context, request)
-                .instrument(request_span)
-                .await?;
+            let mut response = response?;
 
             if display_headers {
-                tracing::info!(response.headers = ?response.headers());
+                tracing::info!(response.headers = ?redactor.headers(response.headers()));
+            }
+            if display_body {
+                response = response.map(|body| {
+                    logging::log_body(
+                        body,
+                        redactor,
+                        move |body| tracing::info!(parent: &request_span, response.body = %body),
+                    )
+                });
             }
 
             Ok(Response { response, context })
@@ -204,34 +359,171 @@ impl Service<Request> for Client {
 }
 
 macro_rules! encode {
-    ($body:expr => $encoder:ident) => {{
-        let mut encoder = $encoder::new(Vec::new());
-        encoder.write_all($body).await?;
-        encoder.shutdown().await?;
-
-        Ok(encoder.into_inner().into())
+    ($body:expr => $encoder:ident, $level:expr) => {{
+        let reader = StreamReader::new(TryStreamExt::map_err($body, io::Error::other));
+        Body::wrap_stream(ReaderStream::new($encoder::with_quality(reader, $level)))
     }};
 }
 
-async fn compress(body: Bytes, headers: &HeaderMap) -> Result<Bytes, BoxError> {
+/// Find the encoding the body should be sent with, from its content-encoding header
+fn content_encoding(headers: &HeaderMap) -> Result<Option<Encoding>, BoxError> {
     let content_encoding = headers
         .get(&CONTENT_ENCODING)
         .map(|header| header.to_str())
-        .transpose()?;
+        .transpose()
+        .map_err(|err| {
+            tracing::error!(compress_error = debug(&err));
+            err
+        })?;
     match content_encoding {
-        Some("br") => encode!(&body => BrotliEncoder),
-        Some("gzip") => encode!(&body => GzipEncoder),
-        Some("deflate") => encode!(&body => ZlibEncoder),
-        Some("identity") | None => Ok(body),
-        Some(encoding) => {
-            tracing::error!(%encoding, "unknown content-encoding value");
-            Err(BoxError::from(format!(
-                "unknown content-encoding {encoding:?}"
-            )))
+        Some("identity") | None => Ok(None),
+        Some(value) => Encoding::from_header(value).map(Some).ok_or_else(|| {
+            tracing::error!(encoding = %value, "unknown content-encoding value");
+            BoxError::from(format!("unknown content-encoding {value:?}"))
+        }),
+    }
+}
+
+/// Encode the body as it's sent, if it has a content-encoding
+///
+/// The encoded length isn't known up front, so any content-length header is removed.
+fn compress(
+    body: Body,
+    headers: &mut HeaderMap,
+    encoding: Option<(Encoding, &CompressionConfig)>,
+) -> Body {
+    let Some((encoding, compression)) = encoding else {
+        return body;
+    };
+
+    let level = compression.level(encoding);
+    let body = match encoding {
+        Encoding::Zstd => encode!(body => ZstdEncoder, level),
+        Encoding::Br => encode!(body => BrotliEncoder, level),
+        Encoding::Gzip => encode!(body => GzipEncoder, level),
+        Encoding::Deflate => encode!(body => ZlibEncoder, level),
+    };
+
+    headers.remove(CONTENT_LENGTH);
+    body
+}
+
+fn host_and_port(uri: &Uri) -> (&str, u16) {
+    let host = uri.host().unwrap_or_default();
+    let port = uri.port_u16().unwrap_or_else(|| {
+        let scheme = uri.scheme_str();
+        match scheme {
+            Some("https") => 443,
+            Some("http") => 80,
+            _ => 0,
+        }
+    });
+
+    (host, port)
+}
+
+/// Whether a response status means the upstream couldn't handle the request
+fn is_unavailable(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// Whether an error means the upstream couldn't be reached or didn't respond in time, rather than
+/// that the request itself was at fault
+fn is_upstream_failure(err: &BoxError) -> bool {
+    if err.is::<PayloadTooLarge>() {
+        return false;
+    }
+
+    // Anything else that isn't from the client is a timeout
+    match err.downcast_ref::<hyper::Error>() {
+        Some(err) => !err.is_user(),
+        None => true,
+    }
+}
+
+/// Read a body into memory so the request can be sent again, as long as its length is known and
+/// within the limit
+///
+/// Bodies are otherwise streamed rather than kept around, so they can only be sent once.
+async fn buffer(body: Body, limit: u64) -> Result<(Body, Option<Bytes>), hyper::Error> {
+    match HttpBody::size_hint(&body).upper() {
+        Some(size) if size <= limit => {
+            let bytes = hyper::body::to_bytes(body).await?;
+            Ok((Body::from(bytes.clone()), Some(bytes)))
         }
+        _ => Ok((body, None)),
     }
 }
 
+/// Send a request, retrying it with the buffered body if the policy allows
+///
+/// Retries share the timeout with the first attempt, so no retry is made once it would start after
+/// the deadline.
+async fn send(
+    client: HttpClient,
+    context: &Context,
+    mut parts: http::request::Parts,
+    body: Body,
+    encoding: Option<(Encoding, &CompressionConfig)>,
+    timeout: Duration,
+    retries: Option<(retry::Retries<'_>, Bytes)>,
+) -> Result<http::Response<Body>, BoxError> {
+    let deadline = tokio::time::Instant::now() + timeout;
+    let Some((mut retries, replay)) = retries else {
+        let body = compress(body, &mut parts.headers, encoding);
+        let request = http::Request::from_parts(parts, body);
+        return fetch_with_timeout(client, context, request, deadline, timeout).await;
+    };
+
+    // The first attempt sends the original body, so that it's still logged
+    let mut body = Some(body);
+    loop {
+        let body = body.take().unwrap_or_else(|| Body::from(replay.clone()));
+        let mut headers = parts.headers.clone();
+        let mut request = http::Request::new(compress(body, &mut headers, encoding));
+        *request.method_mut() = parts.method.clone();
+        *request.uri_mut() = parts.uri.clone();
+        *request.version_mut() = parts.version;
+        *request.headers_mut() = headers;
+
+        let result = fetch_with_timeout(client.clone(), context, request, deadline, timeout).await;
+        let reason = match &result {
+            Ok(response) if retries.retries_status(response.status()) => {
+                format!("status {}", response.status())
+            }
+            Ok(_) => return result,
+            Err(err) => err.to_string(),
+        };
+
+        let attempt = retries.attempt();
+        let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+        let Some(delay) = retries.next(remaining) else {
+            return result;
+        };
+        tracing::info!(attempt, retry_in = ?delay, %reason, "retrying request");
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Fetch a response, giving up at the deadline for the request
+async fn fetch_with_timeout(
+    client: HttpClient,
+    context: &Context,
+    request: http::Request<Body>,
+    deadline: tokio::time::Instant,
+    timeout: Duration,
+) -> Result<http::Response<Body>, BoxError> {
+    tokio::time::timeout_at(deadline, fetch(client, context, request))
+        .await
+        .map_err(|_| {
+            tracing::error!(fetch_error = "timed out", ?timeout);
+            BoxError::from(format!("upstream did not respond within {timeout:?}"))
+        })?
+}
+
 async fn fetch(
     mut client: HttpClient,
     context: &Context,
@@ -277,3 +569,139 @@ where
         self.project().inner.poll_data(cx)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_compression::tokio::bufread::GzipDecoder;
+    use hyper::{server::conn::Http, service::service_fn};
+    use std::{convert::Infallible, sync::Mutex};
+    use tokio::{
+        io::AsyncReadExt,
+        net::{TcpListener, UnixListener},
+    };
+
+    /// Start a server that reads each request body, then responds with 200 OK
+    async fn serve() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let service = service_fn(|request: http::Request<Body>| async move {
+                    let _ = hyper::body::to_bytes(request.into_body()).await;
+                    Ok::<_, Infallible>(http::Response::new(Body::empty()))
+                });
+                tokio::spawn(Http::new().serve_connection(stream, service));
+            }
+        });
+
+        format!("http://{address}/")
+    }
+
+    #[tokio::test]
+    async fn caller_errors_dont_open_the_circuit() {
+        let url = serve().await;
+        let config = serde_json::json!({
+            "http_version": "h1",
+            "circuit_breaker": { "failure_threshold": 1 },
+        });
+        let mut client = Client::new(&serde_json::from_value(config).unwrap()).unwrap();
+
+        let request = http::Request::post(&url)
+            .header(CONTENT_ENCODING, "unknown")
+            .body_with_context(Body::from("{}"), Context::new())
+            .unwrap();
+        let Err(err) = client.call(request).await else {
+            panic!("request should fail");
+        };
+        assert!(
+            err.to_string().contains("unknown content-encoding"),
+            "{err}"
+        );
+
+        let body = Body::wrap_stream(futures::stream::iter([
+            Ok(Bytes::from("{")),
+            Err(io::Error::other("client went away")),
+        ]));
+        let request = http::Request::post(&url)
+            .body_with_context(body, Context::new())
+            .unwrap();
+        let Err(err) = client.call(request).await else {
+            panic!("request should fail");
+        };
+        assert!(!is_upstream_failure(&err), "{err}");
+
+        let request = http::Request::get(&url).context(Context::new()).unwrap();
+        let response = client.call(request).await.unwrap();
+        assert_eq!(response.response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn sends_upstreams_through_their_unix_socket() {
+        let socket = std::env::temp_dir().join(format!("router-test-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&socket);
+        let listener = UnixListener::bind(&socket).unwrap();
+
+        // Record the Host header and decoded body of each request, and respond with a gzipped body
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let requests = received.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let requests = requests.clone();
+                let service = service_fn(move |request: http::Request<Body>| {
+                    let requests = requests.clone();
+                    async move {
+                        let host = request.headers()[http::header::HOST].clone();
+                        let encoding = request.headers()[CONTENT_ENCODING].clone();
+                        let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+                        let mut decoded = String::new();
+                        GzipDecoder::new(&body[..])
+                            .read_to_string(&mut decoded)
+                            .await
+                            .unwrap();
+                        requests.lock().unwrap().push((host, encoding, decoded));
+
+                        let body = Body::wrap_stream(ReaderStream::new(GzipEncoder::new(
+                            &b"from the socket"[..],
+                        )));
+                        let response = http::Response::builder()
+                            .header(CONTENT_ENCODING, "gzip")
+                            .body(body)
+                            .unwrap();
+                        Ok::<_, Infallible>(response)
+                    }
+                });
+                tokio::spawn(Http::new().serve_connection(stream, service));
+            }
+        });
+
+        let config = serde_json::json!({ "unix_sockets": { "users:80": socket } });
+        let mut client = Client::new(&serde_json::from_value(config).unwrap()).unwrap();
+
+        let request = http::Request::post("http://users/graphql")
+            .header(CONTENT_ENCODING, "gzip")
+            .body_with_context(Body::from("{ me }"), Context::new())
+            .unwrap();
+        let response = client.call(request).await.unwrap().response;
+        assert!(!response.headers().contains_key(CONTENT_ENCODING));
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(body, "from the socket");
+        assert_eq!(
+            received.lock().unwrap().as_slice(),
+            [(
+                HeaderValue::from_static("users"),
+                HeaderValue::from_static("gzip"),
+                String::from("{ me }"),
+            )]
+        );
+
+        // Other upstreams are still reached over TCP
+        let request = http::Request::get("http://127.0.0.1:1/")
+            .context(Context::new())
+            .unwrap();
+        assert!(client.call(request).await.is_err());
+        assert_eq!(received.lock().unwrap().len(), 1);
+
+        let _ = std::fs::remove_file(&socket);
+    }
+}
@@ -0,0 +1,223 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Settings for failing fast while an upstream is down
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CircuitBreakerConfig {
+    /// The number of consecutive connection errors, timeouts and 502, 503 or 504 responses that
+    /// opens the circuit. Defaults to 5
+    #[serde(default = "default_failure_threshold")]
+    pub failure_threshold: u32,
+
+    /// How long the circuit stays open before a trial request is let through. Defaults to 10s
+    #[serde(default = "default_open_duration", with = "humantime_serde")]
+    #[schemars(with = "String")]
+    pub open_duration: Duration,
+
+    /// The number of consecutive successful trial requests that closes the circuit again.
+    /// Defaults to 1
+    #[serde(default = "default_success_threshold")]
+    pub success_threshold: u32,
+}
+
+fn default_failure_threshold() -> u32 {
+    5
+}
+
+fn default_open_duration() -> Duration {
+    Duration::from_secs(10)
+}
+
+fn default_success_threshold() -> u32 {
+    1
+}
+
+/// The upstream's circuit is open, so the request was not sent
+#[derive(Debug)]
+pub struct UpstreamUnavailable {
+    pub upstream: String,
+}
+
+impl std::error::Error for UpstreamUnavailable {}
+
+impl Display for UpstreamUnavailable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "upstream {} is unavailable", self.upstream)
+    }
+}
+
+/// Tracks the health of each upstream, keyed by authority, shared between clones of a client
+#[derive(Debug)]
+pub(crate) struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    circuits: Mutex<HashMap<String, Circuit>>,
+}
+
+#[derive(Debug)]
+enum Circuit {
+    /// Requests are sent as usual
+    Closed { failures: u32 },
+    /// Requests are rejected until the deadline
+    Open { until: Instant },
+    /// A single trial request at a time decides whether the upstream has recovered
+    HalfOpen { successes: u32, trial: bool },
+}
+
+impl Circuit {
+    fn name(&self) -> &'static str {
+        match self {
+            Circuit::Closed { .. } => "closed",
+            Circuit::Open { .. } => "open",
+            Circuit::HalfOpen { .. } => "half_open",
+        }
+    }
+}
+
+impl CircuitBreaker {
+    pub(crate) fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            circuits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Check whether a request may be sent to the upstream
+    pub(crate) fn acquire(self: &Arc<Self>, upstream: &str) -> Result<Permit, UpstreamUnavailable> {
+        let mut circuits = self.circuits();
+        let circuit = circuits
+            .entry(upstream.to_owned())
+            .or_insert(Circuit::Closed { failures: 0 });
+
+        if let Circuit::Open { until } = circuit {
+            if Instant::now() >= *until {
+                transition(
+                    upstream,
+                    circuit,
+                    Circuit::HalfOpen {
+                        successes: 0,
+                        trial: false,
+                    },
+                );
+            }
+        }
+
+        let trial = match circuit {
+            Circuit::Closed { .. } => false,
+            Circuit::HalfOpen { trial, .. } if !*trial => {
+                *trial = true;
+                true
+            }
+            Circuit::Open { .. } | Circuit::HalfOpen { .. } => {
+                tracing::info!(
+                    monotonic_counter.http_client_circuit_rejected_total = 1u64,
+                    upstream,
+                );
+                return Err(UpstreamUnavailable {
+                    upstream: upstream.to_owned(),
+                });
+            }
+        };
+
+        Ok(Permit {
+            breaker: self.clone(),
+            upstream: upstream.to_owned(),
+            trial,
+            recorded: false,
+        })
+    }
+
+    fn record(&self, upstream: &str, success: bool) {
+        let mut circuits = self.circuits();
+        let Some(circuit) = circuits.get_mut(upstream) else {
+            return;
+        };
+
+        let next = match circuit {
+            Circuit::Closed { failures } if success => {
+                *failures = 0;
+                None
+            }
+            Circuit::Closed { failures } => {
+                *failures += 1;
+                (*failures >= self.config.failure_threshold).then(|| self.open())
+            }
+            Circuit::HalfOpen { successes, trial } if success => {
+                *successes += 1;
+                *trial = false;
+                (*successes >= self.config.success_threshold)
+                    .then_some(Circuit::Closed { failures: 0 })
+            }
+            Circuit::HalfOpen { .. } => Some(self.open()),
+            // Requests that were sent before the circuit opened
+            Circuit::Open { .. } => None,
+        };
+
+        if let Some(next) = next {
+            transition(upstream, circuit, next);
+        }
+    }
+
+    /// Let another trial request through after one was abandoned
+    fn release(&self, upstream: &str) {
+        if let Some(Circuit::HalfOpen { trial, .. }) = self.circuits().get_mut(upstream) {
+            *trial = false;
+        }
+    }
+
+    fn open(&self) -> Circuit {
+        Circuit::Open {
+            until: Instant::now() + self.config.open_duration,
+        }
+    }
+
+    fn circuits(&self) -> std::sync::MutexGuard<'_, HashMap<String, Circuit>> {
+        self.circuits.lock().expect("circuit breaker lock poisoned")
+    }
+}
+
+fn transition(upstream: &str, circuit: &mut Circuit, next: Circuit) {
+    let state = next.name();
+    tracing::info!(
+        monotonic_counter.http_client_circuit_transitions_total = 1u64,
+        upstream,
+        state,
+    );
+    match next {
+        Circuit::Open { .. } => tracing::warn!(upstream, "circuit opened, upstream is failing"),
+        Circuit::Closed { .. } => tracing::info!(upstream, "circuit closed, upstream recovered"),
+        Circuit::HalfOpen { .. } => {}
+    }
+
+    *circuit = next;
+}
+
+/// Permission to send a request to an upstream, which must be told how the request went
+pub(crate) struct Permit {
+    breaker: Arc<CircuitBreaker>,
+    upstream: String,
+    trial: bool,
+    recorded: bool,
+}
+
+impl Permit {
+    /// Record whether the upstream handled the request
+    pub(crate) fn record(mut self, success: bool) {
+        self.recorded = true;
+        self.breaker.record(&self.upstream, success);
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        if self.trial && !self.recorded {
+            self.breaker.release(&self.upstream);
+        }
+    }
+}
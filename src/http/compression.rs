@@ -0,0 +1,131 @@
+use async_compression::Level;
+use http::HeaderValue;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::fmt::Write;
+
+/// A content-encoding the client can compress requests with and decompress responses from
+#[derive(Clone, Copy, Debug, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoding {
+    Zstd,
+    Br,
+    Gzip,
+    Deflate,
+}
+
+impl Encoding {
+    /// Parse a content-encoding header value
+    pub(crate) fn from_header(value: &str) -> Option<Self> {
+        match value {
+            "zstd" => Some(Encoding::Zstd),
+            "br" => Some(Encoding::Br),
+            "gzip" => Some(Encoding::Gzip),
+            "deflate" => Some(Encoding::Deflate),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Encoding::Zstd => "zstd",
+            Encoding::Br => "br",
+            Encoding::Gzip => "gzip",
+            Encoding::Deflate => "deflate",
+        }
+    }
+}
+
+/// Settings for compressing request bodies and negotiating compressed responses
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CompressionConfig {
+    /// The encodings accepted for responses, most preferred first. Defaults to zstd, gzip, br and
+    /// deflate
+    #[serde(default = "default_accept")]
+    pub accept: Vec<Encoding>,
+
+    /// The level to compress request bodies at for each encoding, where higher is smaller but
+    /// slower. Each defaults to the encoding's own default level
+    #[serde(default)]
+    pub levels: CompressionLevels,
+}
+
+/// Compression levels for each encoding
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CompressionLevels {
+    /// From 1 to 22, or negative for faster levels
+    #[serde(default)]
+    pub zstd: Option<i32>,
+
+    /// From 0 to 11
+    #[serde(default)]
+    pub br: Option<i32>,
+
+    /// From 0 to 9
+    #[serde(default)]
+    pub gzip: Option<i32>,
+
+    /// From 0 to 9
+    #[serde(default)]
+    pub deflate: Option<i32>,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            accept: default_accept(),
+            levels: CompressionLevels::default(),
+        }
+    }
+}
+
+fn default_accept() -> Vec<Encoding> {
+    vec![
+        Encoding::Zstd,
+        Encoding::Gzip,
+        Encoding::Br,
+        Encoding::Deflate,
+    ]
+}
+
+impl CompressionConfig {
+    /// The accept-encoding header, weighting the encodings by their order
+    pub(crate) fn accept_encoding(&self) -> HeaderValue {
+        let mut header = String::new();
+        for (i, encoding) in self.accept.iter().enumerate() {
+            if i > 0 {
+                header.push_str(", ");
+            }
+            header.push_str(encoding.as_str());
+
+            let weight = 10usize.saturating_sub(i).max(1);
+            if weight < 10 {
+                let _ = write!(header, ";q=0.{weight}");
+            }
+        }
+        if header.is_empty() {
+            header.push_str("identity");
+        }
+
+        HeaderValue::from_str(&header).expect("encodings are valid header values")
+    }
+
+    /// Whether responses with the encoding are accepted
+    pub(crate) fn accepts(&self, encoding: Encoding) -> bool {
+        self.accept.contains(&encoding)
+    }
+
+    /// The level to compress request bodies at with the encoding
+    pub(crate) fn level(&self, encoding: Encoding) -> Level {
+        let level = match encoding {
+            Encoding::Zstd => self.levels.zstd,
+            Encoding::Br => self.levels.br,
+            Encoding::Gzip => self.levels.gzip,
+            Encoding::Deflate => self.levels.deflate,
+        };
+
+        level.map_or(Level::Default, Level::Precise)
+    }
+}
@@ -0,0 +1,146 @@
+use super::{CircuitBreakerConfig, CompressionConfig, LoggingConfig, RetryConfig, TlsConfig};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::{collections::HashMap, path::PathBuf, time::Duration};
+
+/// Settings for the HTTP client a plugin uses to reach its upstreams
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ClientConfig {
+    /// How long to wait for a connection to be established. Defaults to 10s
+    #[serde(default = "default_connect_timeout", with = "humantime_serde")]
+    #[schemars(with = "String")]
+    pub connect_timeout: Duration,
+
+    /// How long to wait for the response headers, including connecting, sending the request and
+    /// any retries along with the delays between them. The response body can take longer to
+    /// stream. Defaults to 30s
+    #[serde(default = "default_request_timeout", with = "humantime_serde")]
+    #[schemars(with = "String")]
+    pub request_timeout: Duration,
+
+    /// The largest request body, in bytes, that is sent upstream. Larger requests fail with a
+    /// 413 Payload Too Large error. Unlimited by default
+    #[serde(default)]
+    pub max_request_body_size: Option<u64>,
+
+    /// A Unix socket to send every request through instead of connecting over TCP, unless the
+    /// upstream has its own in `unix_sockets`. The URL's host is still sent in the Host header.
+    /// Connections over the socket use HTTP/1.1 unless `http_version` is set, as HTTP/2 would need
+    /// the upstream to accept it without negotiation
+    #[serde(default)]
+    pub unix_socket: Option<PathBuf>,
+
+    /// Unix sockets to send requests through instead of connecting over TCP, keyed by the host and
+    /// port of the upstream, such as `users:80`. Other upstreams are still reached over TCP, and
+    /// the URL's host is still sent in the Host header
+    #[serde(default)]
+    pub unix_sockets: HashMap<String, PathBuf>,
+
+    /// The HTTP version to use with upstreams. Defaults to HTTP/2, or HTTP/1.1 over a Unix socket.
+    /// If only some upstreams use a Unix socket, the version is negotiated through ALPN instead,
+    /// so that plaintext connections use HTTP/1.1
+    #[serde(default)]
+    pub http_version: Option<HttpVersion>,
+
+    /// How long an idle connection is kept open for reuse. Defaults to 5s
+    #[serde(default = "default_pool_idle_timeout", with = "humantime_serde")]
+    #[schemars(with = "String")]
+    pub pool_idle_timeout: Duration,
+
+    /// The maximum number of idle connections kept open to each upstream
+    #[serde(default)]
+    pub pool_max_idle_per_host: Option<usize>,
+
+    /// The interval between TCP keepalive probes, if any. Defaults to 1m
+    #[serde(default = "default_tcp_keepalive", with = "humantime_serde")]
+    #[schemars(with = "Option<String>")]
+    pub tcp_keepalive: Option<Duration>,
+
+    /// The interval between HTTP/2 keepalive pings, if any
+    #[serde(default, with = "humantime_serde")]
+    #[schemars(with = "Option<String>")]
+    pub http2_keepalive: Option<Duration>,
+
+    /// Which encodings to use for request and response bodies
+    #[serde(default)]
+    pub compression: CompressionConfig,
+
+    /// What to hide when requests and responses are logged
+    #[serde(default)]
+    pub logging: LoggingConfig,
+
+    /// How to secure connections to upstreams
+    #[serde(default)]
+    pub tls: TlsConfig,
+
+    /// Retry idempotent requests that fail. Disabled by default
+    #[serde(default)]
+    pub retry: Option<RetryConfig>,
+
+    /// Reject requests to an upstream immediately while it's failing. Disabled by default
+    #[serde(default)]
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+}
+
+/// Which HTTP version to speak to upstreams
+#[derive(Clone, Copy, Debug, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HttpVersion {
+    /// Only use HTTP/1.1
+    H1,
+    /// Only use HTTP/2, including prior knowledge over plaintext connections
+    H2,
+    /// Negotiate the version through ALPN, using HTTP/1.1 over plaintext connections
+    Auto,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: default_connect_timeout(),
+            request_timeout: default_request_timeout(),
+            max_request_body_size: None,
+            unix_socket: None,
+            unix_sockets: HashMap::new(),
+            http_version: None,
+            pool_idle_timeout: default_pool_idle_timeout(),
+            pool_max_idle_per_host: None,
+            tcp_keepalive: default_tcp_keepalive(),
+            http2_keepalive: None,
+            compression: CompressionConfig::default(),
+            logging: LoggingConfig::default(),
+            tls: TlsConfig::default(),
+            retry: None,
+            circuit_breaker: None,
+        }
+    }
+}
+
+impl ClientConfig {
+    /// The HTTP version to use, falling back to HTTP/1.1 over Unix sockets
+    pub(crate) fn http_version(&self) -> HttpVersion {
+        match self.http_version {
+            Some(version) => version,
+            None if self.unix_socket.is_some() => HttpVersion::H1,
+            None if !self.unix_sockets.is_empty() => HttpVersion::Auto,
+            None => HttpVersion::H2,
+        }
+    }
+}
+
+fn default_connect_timeout() -> Duration {
+    Duration::from_secs(10)
+}
+
+fn default_request_timeout() -> Duration {
+    Duration::from_secs(30)
+}
+
+fn default_pool_idle_timeout() -> Duration {
+    Duration::from_secs(5)
+}
+
+fn default_tcp_keepalive() -> Option<Duration> {
+    Some(Duration::from_secs(60))
+}
@@ -0,0 +1,56 @@
+use futures::StreamExt;
+use http::{header::CONTENT_LENGTH, HeaderMap};
+use hyper::Body;
+use std::{
+    fmt::{Display, Formatter},
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+/// The request body is larger than the client allows, so it wasn't sent in full
+#[derive(Debug)]
+pub struct PayloadTooLarge {
+    pub limit: u64,
+}
+
+impl std::error::Error for PayloadTooLarge {}
+
+impl Display for PayloadTooLarge {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "request body is larger than {} bytes", self.limit)
+    }
+}
+
+/// Whether the request declares a body larger than the limit up front
+pub(crate) fn exceeds(headers: &HeaderMap, limit: u64) -> bool {
+    headers
+        .get(CONTENT_LENGTH)
+        .and_then(|length| length.to_str().ok())
+        .and_then(|length| length.parse::<u64>().ok())
+        .is_some_and(|length| length > limit)
+}
+
+/// Fail the body once more than `limit` bytes have been read from it
+///
+/// The returned flag is set when that happens, so the error that the client reports for the
+/// aborted request can be told apart from the upstream failing.
+pub(crate) fn limit(body: Body, limit: u64) -> (Body, Arc<AtomicBool>) {
+    let exceeded = Arc::new(AtomicBool::new(false));
+
+    let flag = exceeded.clone();
+    let mut read = 0u64;
+    let body = body.map(move |chunk| {
+        let chunk = chunk.map_err(io::Error::other)?;
+        read += chunk.len() as u64;
+        if read > limit {
+            flag.store(true, Ordering::Relaxed);
+            return Err(io::Error::other(PayloadTooLarge { limit }));
+        }
+        Ok(chunk)
+    });
+
+    (Body::wrap_stream(body), exceeded)
+}
@@ -0,0 +1,345 @@
+use futures::Stream;
+use http::{header::HeaderName, HeaderMap, HeaderValue};
+use hyper::{body::Bytes, Body};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Value;
+use std::{
+    io,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+static REDACTED: HeaderValue = HeaderValue::from_static("[REDACTED]");
+
+/// Settings for what is logged when the context asks for request and response details
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct LoggingConfig {
+    /// Headers whose values are replaced with a placeholder. Defaults to authorization, cookie
+    /// and set-cookie
+    #[serde(default = "default_redact_headers")]
+    pub redact_headers: Vec<String>,
+
+    /// JSON paths of body fields whose values are replaced with a placeholder, such as
+    /// `$.variables.password`, `$.items[*].token` or `$..secret`. While any are set, bodies that
+    /// can't be parsed as JSON are replaced entirely
+    #[serde(default)]
+    pub redact_body_fields: Vec<String>,
+
+    /// The most bytes of a body that are logged. Defaults to 4096
+    #[serde(default = "default_max_body_size")]
+    pub max_body_size: usize,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            redact_headers: default_redact_headers(),
+            redact_body_fields: Vec::new(),
+            max_body_size: default_max_body_size(),
+        }
+    }
+}
+
+fn default_redact_headers() -> Vec<String> {
+    vec![
+        String::from("authorization"),
+        String::from("cookie"),
+        String::from("set-cookie"),
+    ]
+}
+
+fn default_max_body_size() -> usize {
+    4096
+}
+
+/// Removes sensitive values from headers and bodies before they're logged
+#[derive(Debug)]
+pub(crate) struct Redactor {
+    headers: Vec<HeaderName>,
+    fields: Vec<Vec<Segment>>,
+    max_body_size: usize,
+}
+
+impl Redactor {
+    pub(crate) fn new(config: &LoggingConfig) -> io::Result<Self> {
+        let headers = config
+            .redact_headers
+            .iter()
+            .map(|name| {
+                HeaderName::from_bytes(name.as_bytes())
+                    .map_err(|_| invalid(format!("invalid header name {name:?}")))
+            })
+            .collect::<io::Result<_>>()?;
+        let fields = config
+            .redact_body_fields
+            .iter()
+            .map(|path| {
+                parse_path(path).ok_or_else(|| invalid(format!("invalid JSON path {path:?}")))
+            })
+            .collect::<io::Result<_>>()?;
+
+        Ok(Self {
+            headers,
+            fields,
+            max_body_size: config.max_body_size,
+        })
+    }
+
+    /// A copy of the headers with the sensitive values replaced
+    pub(crate) fn headers(&self, headers: &HeaderMap) -> HeaderMap {
+        let mut headers = headers.clone();
+        for name in &self.headers {
+            if let http::header::Entry::Occupied(mut entry) = headers.entry(name) {
+                for value in entry.iter_mut() {
+                    *value = REDACTED.clone();
+                }
+            }
+        }
+
+        headers
+    }
+
+    /// Render a body for logging, given whether all of it was captured
+    fn body(&self, body: &[u8], complete: bool) -> String {
+        if !complete {
+            // The fields can't be found without the whole document, so nothing is safe to show
+            if !self.fields.is_empty() {
+                return format!("[body larger than {} bytes]", self.max_body_size);
+            }
+            return format!("{}[truncated]", String::from_utf8_lossy(body));
+        }
+
+        if self.fields.is_empty() {
+            return String::from_utf8_lossy(body).into_owned();
+        }
+
+        // Any fields can't be found in a body that isn't JSON, so none of it is safe to show
+        let Ok(mut document) = serde_json::from_slice::<Value>(body) else {
+            return String::from("[REDACTED: unparseable body]");
+        };
+        for path in &self.fields {
+            mask(&mut document, path);
+        }
+        document.to_string()
+    }
+}
+
+/// Log the body once it has been read, keeping at most the configured number of bytes
+///
+/// Bodies are streamed, so they're captured as they pass through rather than read up front.
+pub(crate) fn log_body<F>(body: Body, redactor: Arc<Redactor>, log: F) -> Body
+where
+    F: FnOnce(String) + Send + Unpin + 'static,
+{
+    Body::wrap_stream(Captured {
+        inner: body,
+        captured: Vec::new(),
+        complete: true,
+        redactor,
+        log: Some(log),
+    })
+}
+
+struct Captured<F: FnOnce(String)> {
+    inner: Body,
+    captured: Vec<u8>,
+    complete: bool,
+    redactor: Arc<Redactor>,
+    log: Option<F>,
+}
+
+impl<F: FnOnce(String)> Captured<F> {
+    fn capture(&mut self, chunk: &Bytes) {
+        let remaining = self.redactor.max_body_size - self.captured.len();
+        if chunk.len() > remaining {
+            self.complete = false;
+        }
+        self.captured
+            .extend_from_slice(&chunk[..chunk.len().min(remaining)]);
+    }
+
+    fn finish(&mut self) {
+        if let Some(log) = self.log.take() {
+            log(self.redactor.body(&self.captured, self.complete));
+        }
+    }
+}
+
+impl<F: FnOnce(String) + Unpin> Stream for Captured<F> {
+    type Item = Result<Bytes, hyper::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let next = Pin::new(&mut self.inner).poll_next(cx);
+        match &next {
+            Poll::Ready(Some(Ok(chunk))) => self.capture(chunk),
+            Poll::Ready(Some(Err(_))) => self.complete = false,
+            Poll::Ready(None) => self.finish(),
+            Poll::Pending => {}
+        }
+
+        next
+    }
+}
+
+impl<F: FnOnce(String)> Drop for Captured<F> {
+    fn drop(&mut self) {
+        // Bodies that weren't read to the end are still logged
+        if self.log.is_some() {
+            self.complete = false;
+            self.finish();
+        }
+    }
+}
+
+/// A step in a JSON path
+#[derive(Debug)]
+struct Segment {
+    /// Whether the selector matches at any depth rather than only the direct children
+    descendant: bool,
+    selector: Selector,
+}
+
+#[derive(Debug)]
+enum Selector {
+    Key(String),
+    Index(usize),
+    Wildcard,
+}
+
+/// Parse a JSON path made of `.key`, `..key`, `[index]` and `*` steps
+fn parse_path(path: &str) -> Option<Vec<Segment>> {
+    let mut rest = path.strip_prefix('$')?;
+    let mut segments = Vec::new();
+
+    while !rest.is_empty() {
+        let (descendant, step) = if let Some(step) = rest.strip_prefix("..") {
+            (true, step)
+        } else if let Some(step) = rest.strip_prefix('.') {
+            (false, step)
+        } else if rest.starts_with('[') {
+            (false, rest)
+        } else {
+            return None;
+        };
+
+        let (selector, remaining) = if let Some(step) = step.strip_prefix('[') {
+            let end = step.find(']')?;
+            let selector = match &step[..end] {
+                "*" => Selector::Wildcard,
+                index => Selector::Index(index.parse().ok()?),
+            };
+            (selector, &step[end + 1..])
+        } else {
+            let end = step.find(['.', '[']).unwrap_or(step.len());
+            let selector = match &step[..end] {
+                "" => return None,
+                "*" => Selector::Wildcard,
+                key => Selector::Key(key.to_owned()),
+            };
+            (selector, &step[end..])
+        };
+
+        segments.push(Segment {
+            descendant,
+            selector,
+        });
+        rest = remaining;
+    }
+
+    (!segments.is_empty()).then_some(segments)
+}
+
+/// Replace every value the path points to
+fn mask(value: &mut Value, path: &[Segment]) {
+    let Some((segment, rest)) = path.split_first() else {
+        *value = Value::String(String::from("[REDACTED]"));
+        return;
+    };
+
+    match (&segment.selector, &mut *value) {
+        (Selector::Key(key), Value::Object(object)) => {
+            if let Some(child) = object.get_mut(key) {
+                mask(child, rest);
+            }
+        }
+        (Selector::Index(index), Value::Array(array)) => {
+            if let Some(child) = array.get_mut(*index) {
+                mask(child, rest);
+            }
+        }
+        (Selector::Wildcard, Value::Object(object)) => {
+            object.values_mut().for_each(|child| mask(child, rest));
+        }
+        (Selector::Wildcard, Value::Array(array)) => {
+            array.iter_mut().for_each(|child| mask(child, rest));
+        }
+        _ => {}
+    }
+
+    if segment.descendant {
+        match value {
+            Value::Object(object) => object.values_mut().for_each(|child| mask(child, path)),
+            Value::Array(array) => array.iter_mut().for_each(|child| mask(child, path)),
+            _ => {}
+        }
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redactor(fields: &[&str]) -> Redactor {
+        Redactor::new(&LoggingConfig {
+            redact_body_fields: fields.iter().map(|&field| field.to_owned()).collect(),
+            ..LoggingConfig::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn masks_body_fields() {
+        let redactor = redactor(&["$.variables.password", "$..token"]);
+        let body = br#"{"variables":{"password":"hunter2","user":{"token":"abc"}}}"#;
+
+        assert_eq!(
+            redactor.body(body, true),
+            r#"{"variables":{"password":"[REDACTED]","user":{"token":"[REDACTED]"}}}"#
+        );
+    }
+
+    #[test]
+    fn hides_bodies_that_cant_be_redacted() {
+        let redactor = redactor(&["$.password"]);
+
+        assert_eq!(
+            redactor.body(b"password=hunter2", true),
+            "[REDACTED: unparseable body]"
+        );
+        assert_eq!(
+            redactor.body(br#"{"password":"hun"#, true),
+            "[REDACTED: unparseable body]"
+        );
+        assert_eq!(
+            redactor.body(b"\xff\xfe", true),
+            "[REDACTED: unparseable body]"
+        );
+        assert_eq!(
+            redactor.body(br#"{"password":"#, false),
+            "[body larger than 4096 bytes]"
+        );
+    }
+
+    #[test]
+    fn logs_bodies_as_is_without_fields() {
+        let redactor = redactor(&[]);
+        assert_eq!(redactor.body(b"password=hunter2", true), "password=hunter2");
+    }
+}
@@ -0,0 +1,188 @@
+use futures::future::BoxFuture;
+use http::{Method, StatusCode, Uri};
+use hyper::client::connect::{Connected, Connection};
+use pin_project_lite::pin_project;
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tower::{BoxError, Service};
+
+/// Record how a request to an upstream went, once its response headers arrived or it failed
+pub(crate) fn record<B>(
+    upstream: &str,
+    method: &Method,
+    result: &Result<http::Response<B>, BoxError>,
+    duration: Duration,
+) {
+    let status = match result {
+        Ok(response) => status_class(response.status()),
+        Err(_) => "error",
+    };
+    let method = method.as_str();
+
+    tracing::info!(
+        monotonic_counter.http_client_requests_total = 1u64,
+        upstream,
+        method,
+        status,
+    );
+    tracing::info!(
+        histogram.http_client_request_duration_seconds = duration.as_secs_f64(),
+        upstream,
+        method,
+        status,
+    );
+}
+
+fn status_class(status: StatusCode) -> &'static str {
+    match status.as_u16() {
+        100..=199 => "1xx",
+        200..=299 => "2xx",
+        300..=399 => "3xx",
+        400..=499 => "4xx",
+        _ => "5xx",
+    }
+}
+
+/// Counts a request as in flight until it's dropped
+pub(crate) struct InFlight {
+    upstream: String,
+}
+
+impl InFlight {
+    pub(crate) fn start(upstream: &str) -> Self {
+        tracing::info!(counter.http_client_requests_in_flight = 1i64, upstream);
+        Self {
+            upstream: upstream.to_owned(),
+        }
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        tracing::info!(
+            counter.http_client_requests_in_flight = -1i64,
+            upstream = %self.upstream,
+        );
+    }
+}
+
+/// Counts a pooled connection as open until it's dropped
+struct OpenConnection {
+    upstream: String,
+}
+
+impl OpenConnection {
+    fn open(upstream: String) -> Self {
+        tracing::info!(counter.http_client_connections = 1i64, %upstream);
+        Self { upstream }
+    }
+}
+
+impl Drop for OpenConnection {
+    fn drop(&mut self) {
+        tracing::info!(
+            counter.http_client_connections = -1i64,
+            upstream = %self.upstream,
+        );
+    }
+}
+
+/// A connector that tracks how many connections are open to each upstream
+#[derive(Clone)]
+pub(crate) struct CountConnections<C> {
+    inner: C,
+}
+
+impl<C> CountConnections<C> {
+    pub(crate) fn new(inner: C) -> Self {
+        Self { inner }
+    }
+}
+
+impl<C> Service<Uri> for CountConnections<C>
+where
+    C: Service<Uri>,
+    C::Future: Send + 'static,
+{
+    type Response = Counted<C::Response>;
+    type Error = C::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let (host, port) = super::host_and_port(&uri);
+        let upstream = format!("{host}:{port}");
+
+        let connecting = self.inner.call(uri);
+        Box::pin(async move {
+            let io = connecting.await?;
+            Ok(Counted {
+                io,
+                _connection: OpenConnection::open(upstream),
+            })
+        })
+    }
+}
+
+pin_project! {
+    /// A connection that is counted while it's open
+    pub(crate) struct Counted<T> {
+        #[pin]
+        io: T,
+        _connection: OpenConnection,
+    }
+}
+
+impl<T: Connection> Connection for Counted<T> {
+    fn connected(&self) -> Connected {
+        self.io.connected()
+    }
+}
+
+impl<T: AsyncRead> AsyncRead for Counted<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        self.project().io.poll_read(cx, buf)
+    }
+}
+
+impl<T: AsyncWrite> AsyncWrite for Counted<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.project().io.poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        self.project().io.poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.io.is_write_vectored()
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().io.poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().io.poll_shutdown(cx)
+    }
+}
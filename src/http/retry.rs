@@ -0,0 +1,159 @@
+use crate::backoff::Backoff;
+use http::{Method, StatusCode};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::time::Duration;
+use tower::retry::budget::Budget;
+
+/// Settings for retrying idempotent requests that fail
+///
+/// Every attempt shares the client's request timeout, so a retry is skipped once its delay would
+/// run past it.
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RetryConfig {
+    /// The most times a request is sent, including the first attempt. Defaults to 3
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+
+    /// The delay before the first retry, which doubles with each one. Defaults to 50ms
+    #[serde(default = "default_initial_backoff", with = "humantime_serde")]
+    #[schemars(with = "String")]
+    pub initial_backoff: Duration,
+
+    /// The longest delay between retries. Defaults to 1s
+    #[serde(default = "default_max_backoff", with = "humantime_serde")]
+    #[schemars(with = "String")]
+    pub max_backoff: Duration,
+
+    /// The response statuses that are retried, along with connection errors and timeouts.
+    /// Defaults to 502, 503 and 504
+    #[serde(default = "default_retry_on_status")]
+    pub retry_on_status: Vec<u16>,
+
+    /// The share of requests that may be retries, on top of the minimum, so that retries can't
+    /// pile up while an upstream is down. Defaults to 0.2
+    #[serde(default = "default_budget_ratio")]
+    pub budget_ratio: f32,
+
+    /// The number of retries allowed per second regardless of the ratio. Defaults to 10
+    #[serde(default = "default_budget_min_per_second")]
+    pub budget_min_per_second: u32,
+
+    /// The largest request body that is kept in memory so it can be sent again. Requests with
+    /// larger bodies, or bodies of unknown length such as chunked ones, are never retried.
+    /// Defaults to 65536
+    #[serde(default = "default_max_buffered_body_size")]
+    pub max_buffered_body_size: u64,
+}
+
+fn default_max_attempts() -> u32 {
+    3
+}
+
+fn default_initial_backoff() -> Duration {
+    Duration::from_millis(50)
+}
+
+fn default_max_backoff() -> Duration {
+    Duration::from_secs(1)
+}
+
+fn default_retry_on_status() -> Vec<u16> {
+    vec![502, 503, 504]
+}
+
+fn default_budget_ratio() -> f32 {
+    0.2
+}
+
+fn default_budget_min_per_second() -> u32 {
+    10
+}
+
+fn default_max_buffered_body_size() -> u64 {
+    64 * 1024
+}
+
+/// Decides which requests are retried, sharing a retry budget between clones of a client
+#[derive(Debug)]
+pub(crate) struct RetryPolicy {
+    config: RetryConfig,
+    budget: Budget,
+}
+
+impl RetryPolicy {
+    pub(crate) fn new(config: RetryConfig) -> Self {
+        let budget = Budget::new(
+            Duration::from_secs(10),
+            config.budget_min_per_second,
+            config.budget_ratio,
+        );
+        Self { config, budget }
+    }
+
+    /// Begin tracking the retries of a request, if its method is safe to retry
+    pub(crate) fn start(&self, method: &Method) -> Option<Retries<'_>> {
+        if !is_idempotent(method) {
+            return None;
+        }
+
+        self.budget.deposit();
+        Some(Retries {
+            policy: self,
+            backoff: Backoff::new(self.config.initial_backoff, self.config.max_backoff),
+        })
+    }
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::PUT | Method::DELETE | Method::TRACE
+    )
+}
+
+/// The retries of a single request
+pub(crate) struct Retries<'a> {
+    policy: &'a RetryPolicy,
+    backoff: Backoff,
+}
+
+impl Retries<'_> {
+    /// Whether a response with the status should be retried
+    pub(crate) fn retries_status(&self, status: StatusCode) -> bool {
+        self.policy
+            .config
+            .retry_on_status
+            .contains(&status.as_u16())
+    }
+
+    /// Record a failed attempt, returning how long to wait before the next one if it's allowed and
+    /// would start within the remaining time
+    pub(crate) fn next(&mut self, remaining: Duration) -> Option<Duration> {
+        if self.backoff.failures() + 1 >= self.policy.config.max_attempts {
+            return None;
+        }
+        let delay = self.backoff.fail();
+        if delay >= remaining {
+            tracing::info!(retry_in = ?delay, "no time left to retry");
+            return None;
+        }
+        if self.policy.budget.withdraw().is_err() {
+            tracing::info!("retry budget exhausted");
+            return None;
+        }
+
+        Some(delay)
+    }
+
+    /// The largest request body that can be kept to send again
+    pub(crate) fn max_buffered_body_size(&self) -> u64 {
+        self.policy.config.max_buffered_body_size
+    }
+
+    /// The number of the attempt about to be made, starting from one
+    pub(crate) fn attempt(&self) -> u32 {
+        self.backoff.failures() + 1
+    }
+}
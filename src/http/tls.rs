@@ -0,0 +1,272 @@
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use rustls::{
+    client::{ResolvesClientCert, ServerCertVerified, ServerCertVerifier, WebPkiVerifier},
+    sign::CertifiedKey,
+    Certificate, PrivateKey, RootCertStore, ServerName, SignatureScheme,
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::{
+    collections::BTreeSet,
+    ffi::OsString,
+    fs,
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::SystemTime,
+};
+
+/// TLS settings for connecting to upstreams
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    /// PEM files of certificate authorities to trust in addition to the system's, reloaded when
+    /// they change
+    #[serde(default)]
+    pub ca_files: Vec<PathBuf>,
+
+    /// A PEM file with the certificate chain to present to upstreams that require mutual TLS,
+    /// reloaded when it changes
+    #[serde(default)]
+    pub client_certificate: Option<PathBuf>,
+
+    /// A PEM file with the private key for the client certificate, reloaded when it changes
+    #[serde(default)]
+    pub client_key: Option<PathBuf>,
+
+    /// The name to send through SNI and to verify the upstream's certificate against, instead of
+    /// the host of the URL
+    #[serde(default)]
+    pub server_name: Option<String>,
+}
+
+/// Build the rustls configuration for a client
+///
+/// When any certificate files are configured, the returned watcher reloads them as they change
+/// and must be kept alive for as long as the configuration is in use.
+pub(crate) fn client_config(
+    config: &TlsConfig,
+) -> io::Result<(rustls::ClientConfig, Option<RecommendedWatcher>)> {
+    let verifier = match config.ca_files.as_slice() {
+        [] => None,
+        files => Some(Arc::new(Reloadable::new(load_verifier(files)?))),
+    };
+    let resolver = match (&config.client_certificate, &config.client_key) {
+        (Some(certificate), Some(key)) => Some(Arc::new(Reloadable::new(load_certified_key(
+            certificate,
+            key,
+        )?))),
+        (None, None) => None,
+        _ => {
+            return Err(invalid(
+                "client_certificate and client_key must be set together",
+            ))
+        }
+    };
+
+    let builder = rustls::ClientConfig::builder().with_safe_defaults();
+    let builder = match &verifier {
+        Some(verifier) => builder.with_custom_certificate_verifier(verifier.clone()),
+        None => builder.with_custom_certificate_verifier(Arc::new(load_verifier(&[])?)),
+    };
+    let tls = match &resolver {
+        Some(resolver) => builder.with_client_cert_resolver(resolver.clone()),
+        None => builder.with_no_client_auth(),
+    };
+
+    let watcher = if verifier.is_some() || resolver.is_some() {
+        Some(watch(config.clone(), verifier, resolver)?)
+    } else {
+        None
+    };
+
+    Ok((tls, watcher))
+}
+
+/// A value that can be swapped out while connections are using it
+struct Reloadable<T>(RwLock<Arc<T>>);
+
+impl<T> Reloadable<T> {
+    fn new(value: T) -> Self {
+        Self(RwLock::new(Arc::new(value)))
+    }
+
+    fn get(&self) -> Arc<T> {
+        self.0.read().expect("tls lock poisoned").clone()
+    }
+
+    fn set(&self, value: T) {
+        *self.0.write().expect("tls lock poisoned") = Arc::new(value);
+    }
+}
+
+impl ServerCertVerifier for Reloadable<WebPkiVerifier> {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        server_name: &ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        self.get().verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            scts,
+            ocsp_response,
+            now,
+        )
+    }
+}
+
+impl ResolvesClientCert for Reloadable<CertifiedKey> {
+    fn resolve(
+        &self,
+        _acceptable_issuers: &[&[u8]],
+        _sigschemes: &[SignatureScheme],
+    ) -> Option<Arc<CertifiedKey>> {
+        Some(self.get())
+    }
+
+    fn has_certs(&self) -> bool {
+        true
+    }
+}
+
+/// Trust the system's certificate authorities along with those in the files
+fn load_verifier(files: &[PathBuf]) -> io::Result<WebPkiVerifier> {
+    let mut roots = RootCertStore::empty();
+    let native = rustls_native_certs::load_native_certs()?
+        .into_iter()
+        .map(|certificate| certificate.0)
+        .collect::<Vec<_>>();
+    roots.add_parsable_certificates(&native);
+
+    for path in files {
+        for certificate in read_certificates(path)? {
+            roots
+                .add(&certificate)
+                .map_err(|err| invalid(format!("invalid CA certificate in {path:?}: {err}")))?;
+        }
+    }
+
+    Ok(WebPkiVerifier::new(roots, None))
+}
+
+fn load_certified_key(certificate: &Path, key: &Path) -> io::Result<CertifiedKey> {
+    let chain = read_certificates(certificate)?;
+
+    let mut reader = BufReader::new(fs::File::open(key)?);
+    let key = rustls_pemfile::read_all(&mut reader)?
+        .into_iter()
+        .find_map(|item| match item {
+            rustls_pemfile::Item::RSAKey(key)
+            | rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::ECKey(key) => Some(PrivateKey(key)),
+            _ => None,
+        })
+        .ok_or_else(|| invalid(format!("no private key found in {key:?}")))?;
+    let key = rustls::sign::any_supported_type(&key)
+        .map_err(|err| invalid(format!("unsupported client key: {err}")))?;
+
+    Ok(CertifiedKey::new(chain, key))
+}
+
+fn read_certificates(path: &Path) -> io::Result<Vec<Certificate>> {
+    let mut reader = BufReader::new(fs::File::open(path)?);
+    let certificates = rustls_pemfile::certs(&mut reader)?;
+    if certificates.is_empty() {
+        return Err(invalid(format!("no certificates found in {path:?}")));
+    }
+
+    Ok(certificates.into_iter().map(Certificate).collect())
+}
+
+/// Reload the certificates whenever one of their files changes
+fn watch(
+    config: TlsConfig,
+    verifier: Option<Arc<Reloadable<WebPkiVerifier>>>,
+    resolver: Option<Arc<Reloadable<CertifiedKey>>>,
+) -> io::Result<RecommendedWatcher> {
+    let paths = config
+        .ca_files
+        .iter()
+        .chain(&config.client_certificate)
+        .chain(&config.client_key)
+        .cloned()
+        .collect::<Vec<_>>();
+    let file_names = paths
+        .iter()
+        .filter_map(|path| path.file_name().map(ToOwned::to_owned))
+        .collect::<BTreeSet<OsString>>();
+
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let Ok(event) = event else { return };
+        // Reading the files while reloading them would otherwise trigger another reload
+        if matches!(event.kind, EventKind::Access(_)) {
+            return;
+        }
+        if !event.paths.iter().any(|path| {
+            path.file_name()
+                .is_some_and(|name| file_names.contains(name))
+        }) {
+            return;
+        }
+
+        reload(&config, verifier.as_deref(), resolver.as_deref());
+    })
+    .map_err(io::Error::other)?;
+
+    // Watch the parent directories so that files replaced by renaming are still picked up
+    let directories = paths
+        .iter()
+        .map(|path| match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => ".".as_ref(),
+        })
+        .collect::<BTreeSet<&Path>>();
+    for directory in directories {
+        watcher
+            .watch(directory, RecursiveMode::NonRecursive)
+            .map_err(io::Error::other)?;
+    }
+
+    Ok(watcher)
+}
+
+/// Replace the certificates, keeping the current ones if the new files are invalid
+fn reload(
+    config: &TlsConfig,
+    verifier: Option<&Reloadable<WebPkiVerifier>>,
+    resolver: Option<&Reloadable<CertifiedKey>>,
+) {
+    tracing::info!("reloading TLS certificates");
+
+    if let Some(verifier) = verifier {
+        match load_verifier(&config.ca_files) {
+            Ok(loaded) => verifier.set(loaded),
+            Err(err) => tracing::error!(
+                code = "HTTP_CLIENT_TLS_RELOAD_FAILURE",
+                "keeping previous CA certificates: {err}"
+            ),
+        }
+    }
+
+    if let (Some(resolver), Some(certificate), Some(key)) =
+        (resolver, &config.client_certificate, &config.client_key)
+    {
+        match load_certified_key(certificate, key) {
+            Ok(loaded) => resolver.set(loaded),
+            Err(err) => tracing::error!(
+                code = "HTTP_CLIENT_TLS_RELOAD_FAILURE",
+                "keeping previous client certificate: {err}"
+            ),
+        }
+    }
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message.into())
+}
@@ -0,0 +1,170 @@
+use futures::future::BoxFuture;
+use http::{uri::Scheme, Uri};
+use hyper::client::connect::{Connected, Connection};
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    net::UnixStream,
+};
+use tower::{BoxError, Service};
+
+/// The Unix sockets that upstreams are reached through instead of TCP
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Sockets {
+    /// The socket for upstreams without one of their own
+    all: Option<Arc<Path>>,
+    /// Sockets keyed by the host and port of an upstream
+    upstreams: Arc<HashMap<String, Arc<Path>>>,
+}
+
+impl Sockets {
+    pub(crate) fn new(all: Option<&Path>, upstreams: &HashMap<String, PathBuf>) -> Self {
+        Self {
+            all: all.map(Arc::from),
+            upstreams: Arc::new(
+                upstreams
+                    .iter()
+                    .map(|(upstream, socket)| (upstream.clone(), Arc::from(socket.as_path())))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// The socket to reach an upstream through, if it isn't reached over TCP
+    pub(crate) fn get(&self, upstream: &str) -> Option<&Arc<Path>> {
+        self.upstreams.get(upstream).or(self.all.as_ref())
+    }
+}
+
+/// A connector that sends requests through a Unix socket rather than over TCP, for any upstreams
+/// that have one configured
+#[derive(Clone)]
+pub(crate) struct UnixConnector<C> {
+    inner: C,
+    sockets: Sockets,
+}
+
+impl<C> UnixConnector<C> {
+    pub(crate) fn new(inner: C, sockets: Sockets) -> Self {
+        Self { inner, sockets }
+    }
+}
+
+impl<C> Service<Uri> for UnixConnector<C>
+where
+    C: Service<Uri>,
+    C::Error: Into<BoxError>,
+    C::Future: Send + 'static,
+{
+    type Response = Stream<C::Response>;
+    type Error = BoxError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let (host, port) = super::host_and_port(&uri);
+        match self.sockets.get(&format!("{host}:{port}")).cloned() {
+            Some(_) if uri.scheme() == Some(&Scheme::HTTPS) => Box::pin(async {
+                Err(BoxError::from(
+                    "https URLs can't be used with a unix socket",
+                ))
+            }),
+            Some(socket) => Box::pin(async move {
+                let stream = UnixStream::connect(&socket).await.map_err(|err| {
+                    io::Error::new(err.kind(), format!("unix socket {socket:?}: {err}"))
+                })?;
+                Ok(Stream::Unix { stream })
+            }),
+            None => {
+                let connecting = self.inner.call(uri);
+                Box::pin(async move {
+                    let stream = connecting.await.map_err(Into::into)?;
+                    Ok(Stream::Inner { stream })
+                })
+            }
+        }
+    }
+}
+
+/// A connection made either by the inner connector or through a Unix socket
+pub(crate) enum Stream<T> {
+    Inner { stream: T },
+    Unix { stream: UnixStream },
+}
+
+impl<T: Connection> Connection for Stream<T> {
+    fn connected(&self) -> Connected {
+        match self {
+            Stream::Inner { stream } => stream.connected(),
+            Stream::Unix { .. } => Connected::new(),
+        }
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for Stream<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Stream::Inner { stream } => Pin::new(stream).poll_read(cx, buf),
+            Stream::Unix { stream } => Pin::new(stream).poll_read(cx, buf),
+        }
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for Stream<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Stream::Inner { stream } => Pin::new(stream).poll_write(cx, buf),
+            Stream::Unix { stream } => Pin::new(stream).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Stream::Inner { stream } => Pin::new(stream).poll_write_vectored(cx, bufs),
+            Stream::Unix { stream } => Pin::new(stream).poll_write_vectored(cx, bufs),
+        }
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        match self {
+            Stream::Inner { stream } => stream.is_write_vectored(),
+            Stream::Unix { stream } => stream.is_write_vectored(),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Stream::Inner { stream } => Pin::new(stream).poll_flush(cx),
+            Stream::Unix { stream } => Pin::new(stream).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Stream::Inner { stream } => Pin::new(stream).poll_shutdown(cx),
+            Stream::Unix { stream } => Pin::new(stream).poll_shutdown(cx),
+        }
+    }
+}
@@ -1,7 +1,10 @@
+mod backoff;
+mod configuration;
 mod hive;
 mod http;
 mod plugins;
 mod responses;
+mod source;
 
 use anyhow::{Context, Result};
 use apollo_router::{Executable, SchemaSource};
@@ -27,9 +30,17 @@ async fn inner_main() -> Result<()> {
     usage::register();
 
     let schema = Box::pin(hive::schema().context("failed to load schema")?);
+    let registry = hive::registry().context("registry was not started")?;
 
-    Executable::builder()
+    let config = configuration::source().context("failed to load router configuration")?;
+
+    let router = Executable::builder()
         .schema(SchemaSource::Stream(schema))
-        .start()
-        .await
+        .and_config(config)
+        .start();
+
+    tokio::select! {
+        result = router => result,
+        result = registry.enforce_startup_timeout() => result,
+    }
 }
@@ -1,5 +1,5 @@
 use crate::{
-    http::{Client, RequestBuilderExt, Response},
+    http::{Client, ClientConfig, RequestBuilderExt, Response, UpstreamUnavailable},
     responses::Responder,
 };
 use apollo_router::{
@@ -16,7 +16,7 @@ use headers::{
     authorization::{Authorization, Bearer},
     HeaderMapExt,
 };
-use http::Method;
+use http::{Method, StatusCode};
 use hyper::body::Buf;
 use schemars::JsonSchema;
 use serde::Deserialize;
@@ -39,6 +39,10 @@ struct Authentication {
 struct Config {
     /// The upstream server for validating authentication tokens
     upstream: Url,
+
+    /// How to connect to the upstream server
+    #[serde(default)]
+    client: ClientConfig,
 }
 
 #[async_trait::async_trait]
@@ -47,7 +51,7 @@ impl Plugin for Authentication {
 
     async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
         Ok(Authentication {
-            client: Client::new()?,
+            client: Client::new(&init.config.client)?,
             upstream: Arc::new(init.config.upstream),
         })
     }
@@ -135,14 +139,22 @@ pub(crate) async fn fetch_context(
         }
     }
 
-    let Response { response, context } = client
+    let result = client
         .call(
             http::Request::builder()
                 .uri(upstream.as_str())
                 .method(Method::GET)
-                .context(req.context)?,
+                .context(req.context.clone())?,
         )
-        .await?;
+        .await;
+    let Response { response, context } = match result {
+        Ok(response) => response,
+        Err(err) if err.is::<UpstreamUnavailable>() => {
+            let message = err.to_string();
+            return Ok(Err(req.respond(message, StatusCode::SERVICE_UNAVAILABLE)?));
+        }
+        Err(err) => return Err(err),
+    };
     req.context = context;
 
     let (parts, body) = response.into_parts();
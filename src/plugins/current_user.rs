@@ -1,5 +1,5 @@
 use super::authentication::fetch_context;
-use crate::http::Client;
+use crate::http::{Client, ClientConfig};
 use apollo_router::{
     plugin::{Plugin, PluginInit},
     register_plugin,
@@ -21,8 +21,15 @@ use url::Url;
 
 register_plugin!("thehackerapp", "current_user", CurrentUser);
 
-#[derive(Debug, Deserialize, JsonSchema)]
 struct CurrentUser {
+    address: ListenAddr,
+    path: String,
+    client: Client,
+    upstream: Arc<Url>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct Config {
     /// The address where the proxy should listen. You'll likely want this to be the same as the
     /// supergraph listen address
     listen: ListenAddr,
@@ -32,28 +39,37 @@ struct CurrentUser {
 
     /// The upstream server for getting authentication info
     upstream: Url,
+
+    /// How to connect to the upstream server
+    #[serde(default)]
+    client: ClientConfig,
 }
 
 #[async_trait::async_trait]
 impl Plugin for CurrentUser {
-    type Config = CurrentUser;
+    type Config = Config;
 
     async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
-        Ok(init.config)
+        Ok(Self {
+            address: init.config.listen,
+            path: init.config.path,
+            client: Client::new(&init.config.client)?,
+            upstream: Arc::new(init.config.upstream),
+        })
     }
 
     fn web_endpoints(&self) -> MultiMap<ListenAddr, Endpoint> {
         let endpoint = Endpoint::from_router_service(
             self.path.clone(),
             CurrentUserService {
-                client: Client::new().expect("client must build"),
-                upstream: Arc::new(self.upstream.clone()),
+                client: self.client.clone(),
+                upstream: self.upstream.clone(),
             }
             .boxed(),
         );
 
         let mut map = MultiMap::with_capacity(1);
-        map.insert(self.listen.clone(), endpoint);
+        map.insert(self.address.clone(), endpoint);
         map
     }
 }
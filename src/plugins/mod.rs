@@ -2,3 +2,5 @@ mod authentication;
 mod current_user;
 mod error;
 mod proxy;
+mod registry;
+mod usage;
@@ -1,4 +1,4 @@
-use crate::http::Client;
+use crate::http::{Body, Client, ClientConfig, PayloadTooLarge, UpstreamUnavailable};
 use apollo_router::{
     plugin::{Plugin, PluginInit},
     register_plugin,
@@ -6,7 +6,10 @@ use apollo_router::{
     Endpoint, ListenAddr,
 };
 use futures::future::BoxFuture;
-use http::uri::{Authority, Scheme, Uri};
+use http::{
+    uri::{Authority, Scheme, Uri},
+    StatusCode,
+};
 use multimap::MultiMap;
 use schemars::JsonSchema;
 use serde::Deserialize;
@@ -33,6 +36,10 @@ struct Config {
 
     /// The routes to transparently proxy through the router
     routes: Vec<Route>,
+
+    /// How to connect to the upstreams
+    #[serde(default)]
+    client: ClientConfig,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
@@ -51,7 +58,7 @@ impl Plugin for Proxy {
     async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
         Ok(Self {
             address: init.config.listen,
-            client: Client::new()?,
+            client: Client::new(&init.config.client)?,
             routes: init.config.routes,
         })
     }
@@ -113,8 +120,25 @@ impl Service<router::Request> for ProxyService {
                 http::Request::from_parts(parts, body)
             };
 
-            let response = client.call(req.into()).await?;
-            Ok(response.into())
+            let context = req.context.clone();
+            match client.call(req.into()).await {
+                Ok(response) => Ok(response.into()),
+                Err(err) if err.is::<UpstreamUnavailable>() || err.is::<PayloadTooLarge>() => {
+                    let status = if err.is::<PayloadTooLarge>() {
+                        StatusCode::PAYLOAD_TOO_LARGE
+                    } else {
+                        StatusCode::SERVICE_UNAVAILABLE
+                    };
+                    let response = http::Response::builder()
+                        .status(status)
+                        .body(Body::from(err.to_string()))?;
+
+                    let mut response = router::Response::from(response);
+                    response.context = context;
+                    Ok(response)
+                }
+                Err(err) => Err(err),
+            }
         })
     }
 }
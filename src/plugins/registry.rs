@@ -0,0 +1,296 @@
+use crate::{
+    hive::{self, PinError, Supergraph},
+    responses::Responder,
+};
+use apollo_router::{
+    plugin::{Plugin, PluginInit},
+    register_plugin,
+    services::{router, supergraph},
+    Endpoint, ListenAddr,
+};
+use futures::future::BoxFuture;
+use headers::{
+    authorization::{Authorization, Bearer},
+    HeaderMapExt,
+};
+use hmac::{Hmac, Mac};
+use http::{header::CONTENT_TYPE, Method, StatusCode};
+use multimap::MultiMap;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+};
+use subtle::ConstantTimeEq;
+use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+
+register_plugin!("thehackerapp", "registry", Registry);
+
+const SIGNATURE_HEADER: &str = "x-signature-256";
+
+struct Registry {
+    config: Config,
+    /// The supergraph the plugin was created for, used to resolve the fields operations use. Usage
+    /// isn't tracked if it couldn't be parsed
+    supergraph: Option<Arc<Supergraph>>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct Config {
+    /// The address where the registry endpoints should listen. You'll likely want this to be the
+    /// same as the supergraph listen address
+    listen: ListenAddr,
+
+    /// The path prefix where the registry endpoints should be served
+    path: String,
+
+    /// The bearer token required to access the admin endpoints
+    admin_token: String,
+
+    /// The secret used to sign schema change webhooks. The webhook endpoint is only served when
+    /// this is set
+    #[serde(default)]
+    webhook_secret: Option<String>,
+
+    #[serde(flatten)]
+    registry: hive::Settings,
+}
+
+#[async_trait::async_trait]
+impl Plugin for Registry {
+    type Config = Config;
+
+    async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+        // Plugins are re-created whenever the configuration is reloaded
+        if let Some(registry) = hive::registry() {
+            init.config.registry.check_startup(registry.startup())?;
+            registry.reconfigure(init.config.registry.clone()).await;
+        }
+
+        let supergraph = match Supergraph::parse(&init.supergraph_sdl) {
+            Ok(supergraph) => Some(Arc::new(supergraph)),
+            Err(err) => {
+                tracing::warn!(
+                    code = "HIVE_REGISTRY_USAGE_DISABLED",
+                    "not tracking field usage for the breaking change guard: {}",
+                    err
+                );
+                None
+            }
+        };
+
+        Ok(Self {
+            config: init.config,
+            supergraph,
+        })
+    }
+
+    fn supergraph_service(&self, service: supergraph::BoxService) -> supergraph::BoxService {
+        let Some(supergraph) = self.supergraph.clone() else {
+            return service;
+        };
+
+        ServiceBuilder::new()
+            .map_request(move |req: supergraph::Request| {
+                // Only pay for copying the operation while the breaking change guard is enabled
+                if let Some(registry) = hive::registry().filter(|r| r.tracks_usage()) {
+                    let body = req.supergraph_request.body();
+                    if let Some(query) = body.query.as_deref() {
+                        let operation_name = body.operation_name.as_deref();
+                        registry.record_usage(&supergraph, query, operation_name);
+                    }
+                }
+
+                req
+            })
+            .service(service)
+            .boxed()
+    }
+
+    fn web_endpoints(&self) -> MultiMap<ListenAddr, Endpoint> {
+        let prefix = self.config.path.trim_end_matches('/');
+        let token = Arc::<str>::from(self.config.admin_token.as_str());
+        let webhook_secret = self.config.webhook_secret.as_deref().map(Arc::<str>::from);
+
+        let mut routes = vec![Route::Status, Route::Schemas, Route::Pin];
+        if webhook_secret.is_some() {
+            routes.push(Route::Webhook);
+        }
+
+        let endpoints = routes.into_iter().map(|route| {
+            Endpoint::from_router_service(
+                format!("{prefix}{}", route.path()),
+                AdminService {
+                    route,
+                    token: token.clone(),
+                    webhook_secret: webhook_secret.clone(),
+                }
+                .boxed(),
+            )
+        });
+
+        let mut map = MultiMap::with_capacity(1);
+        map.insert_many(self.config.listen.clone(), endpoints);
+        map
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Route {
+    /// Report the status of the poller and the active supergraph
+    Status,
+    /// List the recently received supergraphs
+    Schemas,
+    /// Pin or unpin a supergraph
+    Pin,
+    /// Receive signed notifications that the supergraph has changed
+    Webhook,
+}
+
+impl Route {
+    fn path(&self) -> &'static str {
+        match self {
+            Route::Status => "/status",
+            Route::Schemas => "/schemas",
+            Route::Pin => "/pin",
+            Route::Webhook => "/webhook",
+        }
+    }
+
+    /// Whether the admin token is required to access the route
+    fn is_admin(&self) -> bool {
+        !matches!(self, Route::Status | Route::Webhook)
+    }
+}
+
+struct AdminService {
+    route: Route,
+    token: Arc<str>,
+    webhook_secret: Option<Arc<str>>,
+}
+
+impl Service<router::Request> for AdminService {
+    type Response = router::Response;
+    type Error = BoxError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, mut req: router::Request) -> Self::Future {
+        let route = self.route;
+        let token = self.token.clone();
+        let webhook_secret = self.webhook_secret.clone();
+
+        Box::pin(async move {
+            if route.is_admin() && !is_authorized(&req, &token) {
+                return req.respond("invalid admin token", StatusCode::UNAUTHORIZED);
+            }
+
+            let Some(registry) = hive::registry() else {
+                return req.respond("registry is not running", StatusCode::SERVICE_UNAVAILABLE);
+            };
+
+            let method = req.router_request.method().clone();
+            let result = match (route, method) {
+                (Route::Status, Method::GET) => return json(req, &registry.status()),
+                (Route::Schemas, Method::GET) => Ok(()),
+                (Route::Pin, Method::POST) => {
+                    let body = std::mem::take(req.router_request.body_mut());
+                    let body = hyper::body::to_bytes(body).await?;
+                    let Ok(PinRequest { hash }) = serde_json::from_slice(&body) else {
+                        return req.respond_invalid("expected a JSON body with a schema hash");
+                    };
+
+                    registry.pin(&hash).await
+                }
+                (Route::Pin, Method::DELETE) => registry.unpin().await,
+                (Route::Webhook, Method::POST) => {
+                    let body = std::mem::take(req.router_request.body_mut());
+                    let body = hyper::body::to_bytes(body).await?;
+
+                    let secret = webhook_secret.as_deref().unwrap_or_default();
+                    if !is_signed(&req, &body, secret) {
+                        return req.respond("invalid webhook signature", StatusCode::UNAUTHORIZED);
+                    }
+
+                    registry.refresh("webhook");
+                    let accepted = serde_json::json!({ "status": "ACCEPTED" });
+                    return json_with_status(req, &accepted, StatusCode::ACCEPTED);
+                }
+                _ => return req.respond("method not allowed", StatusCode::METHOD_NOT_ALLOWED),
+            };
+
+            match result {
+                Ok(()) => json(req, &registry.schemas()),
+                Err(e @ PinError::InvalidHash) => req.respond_invalid(e.to_string()),
+                Err(e @ PinError::UnknownSchema) => {
+                    req.respond(e.to_string(), StatusCode::NOT_FOUND)
+                }
+                Err(e @ PinError::Unavailable) => {
+                    req.respond(e.to_string(), StatusCode::SERVICE_UNAVAILABLE)
+                }
+            }
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct PinRequest {
+    hash: String,
+}
+
+/// Check the request's bearer token against the configured admin token
+fn is_authorized(req: &router::Request, token: &str) -> bool {
+    req.router_request
+        .headers()
+        .typed_get::<Authorization<Bearer>>()
+        .map(|auth| bool::from(auth.token().as_bytes().ct_eq(token.as_bytes())))
+        .unwrap_or(false)
+}
+
+/// Check the request's `X-Signature-256` header, a hex-encoded HMAC-SHA256 of the body
+fn is_signed(req: &router::Request, body: &[u8], secret: &str) -> bool {
+    let Some(signature) = req
+        .router_request
+        .headers()
+        .get(SIGNATURE_HEADER)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return false;
+    };
+
+    let signature = signature.strip_prefix("sha256=").unwrap_or(signature);
+    let Ok(signature) = hex::decode(signature) else {
+        return false;
+    };
+
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC can take a key of any size");
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+/// Respond with a JSON body
+fn json<T: Serialize>(req: router::Request, value: &T) -> Result<router::Response, BoxError> {
+    json_with_status(req, value, StatusCode::OK)
+}
+
+/// Respond with a JSON body and status code
+fn json_with_status<T: Serialize>(
+    req: router::Request,
+    value: &T,
+    status: StatusCode,
+) -> Result<router::Response, BoxError> {
+    let body = hyper::Body::from(serde_json::to_vec(value)?);
+    let response = router::Response::builder()
+        .status_code(status)
+        .header(CONTENT_TYPE, "application/json")
+        .context(req.context)
+        .build()?;
+
+    Ok(response.map(|_body| body))
+}
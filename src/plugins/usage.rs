@@ -0,0 +1,356 @@
+use super::authentication::AUTHENTICATION_SCOPE_CONTEXT_KEY;
+use crate::hive::{self, Supergraph};
+use apollo_router::{
+    plugin::{Plugin, PluginInit},
+    register_plugin,
+    services::supergraph,
+};
+use context::{headers::EventSlug, Scope};
+use futures::future::BoxFuture;
+use headers::HeaderMapExt;
+use http::{HeaderMap, HeaderName, HeaderValue};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    path::PathBuf,
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Instant, SystemTime},
+};
+use tokio::{
+    io::{AsyncWrite, AsyncWriteExt},
+    sync::mpsc,
+};
+use tower::{BoxError, Service, ServiceBuilder, ServiceExt};
+
+/// The header the resolved client name is written to, for `hive.usage.client_name_header`
+const CLIENT_NAME_HEADER: HeaderName = HeaderName::from_static("x-usage-client-name");
+/// The header the resolved client version is written to, for `hive.usage.client_version_header`
+const CLIENT_VERSION_HEADER: HeaderName = HeaderName::from_static("x-usage-client-version");
+
+register_plugin!("thehackerapp", "usage", Usage);
+
+/// Identifies the client of each operation for Hive usage reporting
+///
+/// This must be listed before `hive.usage` so that the client is identified before it's reported.
+struct Usage {
+    config: Arc<Identification>,
+    sink: Option<Arc<Sink>>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct Config {
+    /// The request headers to read the client name from, in order of preference
+    #[serde(default = "default_client_name_headers")]
+    client_name_headers: Vec<String>,
+
+    /// The request headers to read the client version from, in order of preference
+    #[serde(default = "default_client_version_headers")]
+    client_version_headers: Vec<String>,
+
+    /// Prefix the client name with the slug of the event resolved by the authentication plugin, as
+    /// `event/client`
+    #[serde(default)]
+    tag_event: bool,
+
+    /// Also write a record of every operation locally, for offline analysis
+    #[serde(default)]
+    sink: Option<SinkConfig>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct SinkConfig {
+    /// The file to append JSON lines to. Defaults to standard output
+    #[serde(default)]
+    path: Option<PathBuf>,
+
+    /// The operations (by name) to leave out of the records
+    #[serde(default)]
+    exclude: Vec<String>,
+}
+
+fn default_client_name_headers() -> Vec<String> {
+    vec![
+        String::from("apollographql-client-name"),
+        String::from("graphql-client-name"),
+    ]
+}
+
+fn default_client_version_headers() -> Vec<String> {
+    vec![
+        String::from("apollographql-client-version"),
+        String::from("graphql-client-version"),
+    ]
+}
+
+#[derive(Debug)]
+struct Identification {
+    client_name_headers: Vec<HeaderName>,
+    client_version_headers: Vec<HeaderName>,
+    tag_event: bool,
+}
+
+#[async_trait::async_trait]
+impl Plugin for Usage {
+    type Config = Config;
+
+    async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+        let parse = |names: Vec<String>| {
+            names
+                .into_iter()
+                .map(|name| HeaderName::try_from(name).map_err(BoxError::from))
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        let sink = match init.config.sink {
+            Some(config) => match Supergraph::parse(&init.supergraph_sdl) {
+                Ok(supergraph) => Some(Arc::new(Sink {
+                    supergraph,
+                    exclude: config.exclude,
+                    records: spawn_writer(config.path),
+                })),
+                Err(err) => {
+                    tracing::warn!(
+                        code = "USAGE_SINK_DISABLED",
+                        "not writing usage records for this supergraph: {}",
+                        err
+                    );
+                    None
+                }
+            },
+            None => None,
+        };
+
+        Ok(Usage {
+            config: Arc::new(Identification {
+                client_name_headers: parse(init.config.client_name_headers)?,
+                client_version_headers: parse(init.config.client_version_headers)?,
+                tag_event: init.config.tag_event,
+            }),
+            sink,
+        })
+    }
+
+    fn supergraph_service(&self, service: supergraph::BoxService) -> supergraph::BoxService {
+        let config = self.config.clone();
+        let service = match &self.sink {
+            Some(sink) => Recorder {
+                inner: service,
+                sink: sink.clone(),
+            }
+            .boxed(),
+            None => service,
+        };
+
+        ServiceBuilder::new()
+            .map_request(move |mut req: supergraph::Request| {
+                let headers = req.supergraph_request.headers();
+                let mut name = first_header(headers, &config.client_name_headers);
+                let version = first_header(headers, &config.client_version_headers);
+
+                if config.tag_event {
+                    if let Some(event) = event_slug(&req) {
+                        let client = name.as_ref().and_then(|name| name.to_str().ok());
+                        let tagged = format!("{event}/{}", client.unwrap_or("unknown"));
+                        name = HeaderValue::from_str(&tagged).ok();
+                    }
+                }
+
+                let headers = req.supergraph_request.headers_mut();
+                if let Some(name) = name {
+                    headers.insert(CLIENT_NAME_HEADER, name);
+                }
+                if let Some(version) = version {
+                    headers.insert(CLIENT_VERSION_HEADER, version);
+                }
+
+                req
+            })
+            .service(service)
+            .boxed()
+    }
+}
+
+/// Get the value of the first header that's present
+fn first_header(headers: &HeaderMap, names: &[HeaderName]) -> Option<HeaderValue> {
+    names.iter().find_map(|name| headers.get(name)).cloned()
+}
+
+/// Get the slug of the event the request was scoped to by the authentication plugin
+fn event_slug(req: &supergraph::Request) -> Option<String> {
+    let scope = req
+        .context
+        .get::<_, Scope>(AUTHENTICATION_SCOPE_CONTEXT_KEY)
+        .ok()
+        .flatten()?;
+
+    // The scope exposes its event the same way it's passed on to subgraphs
+    let mut headers = HeaderMap::new();
+    scope.write_headers(&mut headers);
+    headers
+        .typed_get::<EventSlug>()
+        .map(|slug| slug.to_string())
+}
+
+/// Writes usage records locally
+struct Sink {
+    supergraph: Supergraph,
+    exclude: Vec<String>,
+    records: mpsc::Sender<Record>,
+}
+
+/// The usage of a single operation
+#[derive(Debug, Serialize)]
+struct Record {
+    timestamp: String,
+    operation_name: Option<String>,
+    /// The hex-encoded SHA-256 hash of the query
+    operation_hash: String,
+    /// The schema coordinates of the fields and arguments used
+    fields: Vec<String>,
+    latency_ms: f64,
+    status: u16,
+    client: Client,
+    event: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Client {
+    name: Option<String>,
+    version: Option<String>,
+}
+
+/// Records each operation once it completes
+///
+/// Wraps the rest of the supergraph service, so the client has already been identified.
+struct Recorder {
+    inner: supergraph::BoxService,
+    sink: Arc<Sink>,
+}
+
+impl Service<supergraph::Request> for Recorder {
+    type Response = supergraph::Response;
+    type Error = BoxError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: supergraph::Request) -> Self::Future {
+        let sink = self.sink.clone();
+        let start = Instant::now();
+        let record = sink.start(&req);
+        let response = self.inner.call(req);
+
+        Box::pin(async move {
+            let response = response.await?;
+
+            if let Some(mut record) = record {
+                record.latency_ms = start.elapsed().as_secs_f64() * 1000.0;
+                record.status = response.response.status().as_u16();
+
+                if sink.records.try_send(record).is_err() {
+                    tracing::warn!(
+                        code = "USAGE_SINK_FULL",
+                        "dropping usage record, sink is not keeping up"
+                    );
+                }
+            }
+
+            Ok(response)
+        })
+    }
+}
+
+impl Sink {
+    /// Begin a record for the request, if it should be recorded
+    fn start(&self, req: &supergraph::Request) -> Option<Record> {
+        let body = req.supergraph_request.body();
+        let query = body.query.as_deref()?;
+        let operation_name = body.operation_name.clone();
+        if operation_name
+            .as_ref()
+            .is_some_and(|name| self.exclude.contains(name))
+        {
+            return None;
+        }
+
+        // Invalid operations are still recorded, the router reports why they failed
+        let fields = hive::coordinates(&self.supergraph, query, operation_name.as_deref())
+            .map(|fields| fields.into_iter().collect())
+            .unwrap_or_default();
+
+        let headers = req.supergraph_request.headers();
+        let header = |name: &HeaderName| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(ToOwned::to_owned)
+        };
+
+        Some(Record {
+            timestamp: humantime::format_rfc3339_millis(SystemTime::now()).to_string(),
+            operation_name,
+            operation_hash: hex::encode(Sha256::digest(query.as_bytes())),
+            fields,
+            latency_ms: 0.0,
+            status: 0,
+            client: Client {
+                name: header(&CLIENT_NAME_HEADER),
+                version: header(&CLIENT_VERSION_HEADER),
+            },
+            event: event_slug(req),
+        })
+    }
+}
+
+/// Start writing records as JSON lines in the background
+fn spawn_writer(path: Option<PathBuf>) -> mpsc::Sender<Record> {
+    let (sender, mut records) = mpsc::channel::<Record>(1024);
+
+    drop(tokio::task::spawn(async move {
+        let mut output: Box<dyn AsyncWrite + Send + Unpin> = match &path {
+            Some(path) => {
+                let file = tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await;
+                match file {
+                    Ok(file) => Box::new(file),
+                    Err(err) => {
+                        tracing::error!(
+                            code = "USAGE_SINK_FAILURE",
+                            path = %path.display(),
+                            "failed to open usage sink: {err}"
+                        );
+                        return;
+                    }
+                }
+            }
+            None => Box::new(tokio::io::stdout()),
+        };
+
+        // The writer stops once the plugin has been replaced and its in-flight requests complete
+        while let Some(record) = records.recv().await {
+            let mut line = serde_json::to_vec(&record).expect("record must serialize");
+            line.push(b'\n');
+
+            let result = async {
+                output.write_all(&line).await?;
+                output.flush().await
+            };
+            if let Err(err) = result.await {
+                tracing::error!(
+                    code = "USAGE_SINK_FAILURE",
+                    "failed to write usage record: {err}"
+                );
+            }
+        }
+    }));
+
+    sender
+}
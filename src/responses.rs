@@ -61,7 +61,10 @@ where
         .message(message)
         .extension_code(match code {
             StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY => "BAD_REQUEST",
+            StatusCode::UNAUTHORIZED => "UNAUTHENTICATED",
+            StatusCode::NOT_FOUND => "NOT_FOUND",
             StatusCode::INTERNAL_SERVER_ERROR => "INTERNAL_ERROR",
+            StatusCode::SERVICE_UNAVAILABLE => "SERVICE_UNAVAILABLE",
             _ => "UNKNOWN",
         })
         .build()
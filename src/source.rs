@@ -0,0 +1,58 @@
+use anyhow::Result;
+use http::{header, HeaderMap, HeaderValue};
+use reqwest::{Client, ClientBuilder};
+use std::time::Duration;
+
+mod file;
+mod remote;
+#[cfg(test)]
+pub(crate) mod testing;
+
+pub(crate) use file::File;
+pub(crate) use remote::{Endpoint, Remote};
+
+static COMMIT: Option<&'static str> = option_env!("GITHUB_SHA");
+
+/// A document that is polled for changes, such as the supergraph or the router configuration
+#[async_trait::async_trait]
+pub(crate) trait Source: Send {
+    /// Fetch the current contents of the source
+    async fn fetch(&mut self) -> Fetch;
+
+    /// Restore the state of a previous fetch, such as from a cached copy
+    fn restore(&mut self, _source: &str, _etag: Option<String>) {}
+
+    /// Wait until the source should be checked again
+    async fn wait(&mut self, delay: Duration) {
+        tokio::time::sleep(delay).await
+    }
+}
+
+/// The outcome of checking a source for new contents
+pub(crate) struct Fetch {
+    /// Where the contents were fetched from
+    pub source: String,
+    /// The ETag of the source's current contents
+    pub etag: Option<String>,
+    /// The fetched contents, if they might have changed
+    pub result: Result<Option<String>>,
+}
+
+/// Build an HTTP client for fetching sources
+pub(crate) fn client(timeout: Duration) -> Result<Client> {
+    Ok(client_builder().timeout(timeout).build()?)
+}
+
+/// Start building an HTTP client that identifies the router
+pub(crate) fn client_builder() -> ClientBuilder {
+    let headers = {
+        let mut map = HeaderMap::new();
+        map.insert(
+            header::USER_AGENT,
+            HeaderValue::from_str(&format!("apollo-router/{}", COMMIT.unwrap_or("local"))).unwrap(),
+        );
+        map
+    };
+
+    Client::builder().default_headers(headers)
+}
@@ -0,0 +1,147 @@
+use super::{Fetch, Source};
+use anyhow::{Context, Result};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::{path::PathBuf, time::Duration};
+use tokio::sync::mpsc;
+
+/// Reads a local file, reloading it when it changes
+pub(crate) struct File {
+    path: PathBuf,
+    changes: mpsc::Receiver<()>,
+    _watcher: RecommendedWatcher,
+}
+
+impl File {
+    pub(crate) fn new(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let (sender, changes) = mpsc::channel(1);
+
+        // Watch the parent directory so that files replaced by renaming are still picked up
+        let file_name = path.file_name().map(ToOwned::to_owned);
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                let Ok(event) = event else { return };
+                if event
+                    .paths
+                    .iter()
+                    .any(|path| path.file_name() == file_name.as_deref())
+                {
+                    // A change is already pending if the channel is full
+                    let _ = sender.try_send(());
+                }
+            })
+            .context("failed to create file watcher")?;
+
+        let directory = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => ".".as_ref(),
+        };
+        watcher
+            .watch(directory, RecursiveMode::NonRecursive)
+            .with_context(|| format!("failed to watch {}", directory.display()))?;
+
+        Ok(Self {
+            path,
+            changes,
+            _watcher: watcher,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl Source for File {
+    async fn fetch(&mut self) -> Fetch {
+        let result = tokio::fs::read_to_string(&self.path)
+            .await
+            .with_context(|| format!("failed to read {}", self.path.display()));
+
+        Fetch {
+            source: self.path.display().to_string(),
+            etag: None,
+            result: result.map(Some),
+        }
+    }
+
+    async fn wait(&mut self, delay: Duration) {
+        tokio::select! {
+            _ = self.changes.recv() => {},
+            _ = tokio::time::sleep(delay) => {},
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Long enough that a returning wait must have been woken by a change
+    const DELAY: Duration = Duration::from_secs(60);
+
+    async fn changed(file: &mut File) -> bool {
+        tokio::time::timeout(Duration::from_secs(5), file.wait(DELAY))
+            .await
+            .is_ok()
+    }
+
+    #[tokio::test]
+    async fn reloads_on_write() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("supergraph.graphql");
+        std::fs::write(&path, "type Query { a: Int }").unwrap();
+
+        let mut file = File::new(&path).unwrap();
+        let fetch = file.fetch().await;
+        assert_eq!(fetch.source, path.display().to_string());
+        assert_eq!(
+            fetch.result.unwrap().as_deref(),
+            Some("type Query { a: Int }")
+        );
+
+        std::fs::write(&path, "type Query { b: Int }").unwrap();
+        assert!(changed(&mut file).await);
+        assert_eq!(
+            file.fetch().await.result.unwrap().as_deref(),
+            Some("type Query { b: Int }")
+        );
+    }
+
+    #[tokio::test]
+    async fn reloads_on_rename() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("supergraph.graphql");
+        std::fs::write(&path, "type Query { a: Int }").unwrap();
+
+        let mut file = File::new(&path).unwrap();
+        file.fetch().await.result.unwrap();
+
+        let staged = directory.path().join("supergraph.graphql.tmp");
+        std::fs::write(&staged, "type Query { b: Int }").unwrap();
+        std::fs::rename(&staged, &path).unwrap();
+        assert!(changed(&mut file).await);
+        assert_eq!(
+            file.fetch().await.result.unwrap().as_deref(),
+            Some("type Query { b: Int }")
+        );
+    }
+
+    #[tokio::test]
+    async fn ignores_its_own_reads() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("supergraph.graphql");
+        std::fs::write(&path, "type Query { a: Int }").unwrap();
+
+        let mut file = File::new(&path).unwrap();
+        file.fetch().await.result.unwrap();
+
+        let woken = tokio::time::timeout(Duration::from_millis(500), file.wait(DELAY)).await;
+        assert!(woken.is_err());
+    }
+
+    #[tokio::test]
+    async fn reports_missing_file() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut file = File::new(directory.path().join("missing.graphql")).unwrap();
+
+        assert!(file.fetch().await.result.is_err());
+    }
+}
@@ -0,0 +1,158 @@
+use super::{Fetch, Source};
+use http::{header, HeaderMap, HeaderValue, StatusCode};
+use reqwest::Client;
+use url::Url;
+
+/// An HTTP endpoint serving a document, polled using ETags
+#[derive(Debug)]
+pub(crate) struct Endpoint {
+    url: Url,
+    headers: HeaderMap,
+    etag: Option<String>,
+}
+
+impl Endpoint {
+    pub(crate) fn new(url: Url, headers: HeaderMap) -> Self {
+        Self {
+            url,
+            headers,
+            etag: None,
+        }
+    }
+
+    pub(crate) fn url(&self) -> &Url {
+        &self.url
+    }
+
+    pub(crate) fn etag(&self) -> Option<&str> {
+        self.etag.as_deref()
+    }
+
+    pub(crate) fn set_etag(&mut self, etag: Option<String>) {
+        self.etag = etag;
+    }
+
+    /// Fetch the document, returning `None` if it hasn't changed since the last fetch
+    pub(crate) async fn fetch(
+        &mut self,
+        client: &Client,
+    ) -> Result<Option<String>, reqwest::Error> {
+        let mut request = client.get(self.url.as_str()).headers(self.headers.clone());
+        if let Some(etag) = self.etag.as_deref() {
+            request = request.header(header::IF_NONE_MATCH, HeaderValue::from_str(etag).unwrap());
+        }
+
+        let response = request.send().await?.error_for_status()?;
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+
+        let etag = response
+            .headers()
+            .get(header::ETAG)
+            .and_then(|etag| etag.to_str().ok())
+            .map(ToOwned::to_owned);
+        let schema = response.text().await?;
+
+        self.etag = etag;
+        Ok(Some(schema))
+    }
+}
+
+/// Polls a generic HTTP URL using ETags
+pub(crate) struct Remote {
+    client: Client,
+    endpoint: Endpoint,
+}
+
+impl Remote {
+    pub(crate) fn new(client: Client, endpoint: Endpoint) -> Self {
+        Self { client, endpoint }
+    }
+}
+
+#[async_trait::async_trait]
+impl Source for Remote {
+    async fn fetch(&mut self) -> Fetch {
+        let result = self.endpoint.fetch(&self.client).await;
+
+        Fetch {
+            source: self.endpoint.url().to_string(),
+            etag: self.endpoint.etag().map(ToOwned::to_owned),
+            result: result.map_err(Into::into),
+        }
+    }
+
+    fn restore(&mut self, source: &str, etag: Option<String>) {
+        if self.endpoint.url().as_str() == source {
+            self.endpoint.set_etag(etag);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{client, testing::Server};
+    use super::*;
+    use std::time::Duration;
+
+    fn remote(server: &Server) -> Remote {
+        let client = client(Duration::from_secs(5)).unwrap();
+        Remote::new(client, Endpoint::new(server.url(), HeaderMap::new()))
+    }
+
+    #[tokio::test]
+    async fn only_fetches_changed_schemas() {
+        let server = Server::start("type Query { a: Int }").await;
+        let mut remote = remote(&server);
+
+        let fetch = remote.fetch().await;
+        assert_eq!(fetch.source, server.url().as_str());
+        assert_eq!(fetch.etag, Some(server.etag()));
+        assert_eq!(
+            fetch.result.unwrap().as_deref(),
+            Some("type Query { a: Int }")
+        );
+
+        let fetch = remote.fetch().await;
+        assert_eq!(fetch.result.unwrap(), None);
+        let requests = server.requests();
+        assert_eq!(
+            requests[1].get(header::IF_NONE_MATCH).unwrap(),
+            server.etag().as_str()
+        );
+
+        server.set_schema("type Query { b: Int }");
+        let fetch = remote.fetch().await;
+        assert_eq!(fetch.etag, Some(server.etag()));
+        assert_eq!(
+            fetch.result.unwrap().as_deref(),
+            Some("type Query { b: Int }")
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_errors() {
+        let server = Server::start("type Query { a: Int }").await;
+        let mut remote = remote(&server);
+
+        server.set_status(StatusCode::SERVICE_UNAVAILABLE);
+        assert!(remote.fetch().await.result.is_err());
+
+        server.set_status(StatusCode::OK);
+        assert!(remote.fetch().await.result.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn restores_etag_for_matching_source() {
+        let server = Server::start("type Query { a: Int }").await;
+        let mut remote = remote(&server);
+
+        remote.restore("http://elsewhere/supergraph", Some(server.etag()));
+        assert!(remote.fetch().await.result.unwrap().is_some());
+
+        let mut remote = self::remote(&server);
+        remote.restore(server.url().as_str(), Some(server.etag()));
+        assert_eq!(remote.fetch().await.result.unwrap(), None);
+    }
+}
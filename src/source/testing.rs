@@ -0,0 +1,119 @@
+//! A stand-in for the servers that sources are fetched from
+
+use http::{header, HeaderMap, HeaderValue, Request, Response, StatusCode};
+use hyper::{server::conn::Http, service::service_fn, Body};
+use sha2::{Digest, Sha256};
+use std::{
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+use tokio::net::TcpListener;
+use url::Url;
+
+/// Serves a schema with an ETag, answering conditional requests with 304 Not Modified
+pub(crate) struct Server {
+    address: SocketAddr,
+    state: Arc<Mutex<State>>,
+}
+
+struct State {
+    status: StatusCode,
+    schema: String,
+    etag: String,
+    requests: Vec<HeaderMap>,
+}
+
+impl Server {
+    pub(crate) async fn start(schema: &str) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let state = Arc::new(Mutex::new(State {
+            status: StatusCode::OK,
+            schema: String::new(),
+            etag: String::new(),
+            requests: Vec::new(),
+        }));
+
+        let shared = state.clone();
+        tokio::spawn(async move {
+            loop {
+                let Ok((stream, _)) = listener.accept().await else {
+                    return;
+                };
+                let state = shared.clone();
+                let service = service_fn(move |request| {
+                    let response = respond(&state, request);
+                    async move { Ok::<_, Infallible>(response) }
+                });
+                tokio::spawn(Http::new().serve_connection(stream, service));
+            }
+        });
+
+        let server = Self { address, state };
+        server.set_schema(schema);
+        server
+    }
+
+    pub(crate) fn url(&self) -> Url {
+        format!("http://{}/supergraph", self.address)
+            .parse()
+            .unwrap()
+    }
+
+    /// Serve a new schema, with an ETag derived from it
+    pub(crate) fn set_schema(&self, schema: &str) {
+        let mut state = self.state();
+        state.schema = schema.to_owned();
+        state.etag = format!(
+            "\"{}\"",
+            hex::encode(&Sha256::digest(schema.as_bytes())[..8])
+        );
+    }
+
+    /// Fail every request with the status, or recover with 200 OK
+    pub(crate) fn set_status(&self, status: StatusCode) {
+        self.state().status = status;
+    }
+
+    pub(crate) fn etag(&self) -> String {
+        self.state().etag.clone()
+    }
+
+    /// The headers of every request received so far
+    pub(crate) fn requests(&self) -> Vec<HeaderMap> {
+        self.state().requests.clone()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+}
+
+fn respond(state: &Mutex<State>, request: Request<Body>) -> Response<Body> {
+    let mut state = state.lock().unwrap();
+    state.requests.push(request.headers().clone());
+
+    if state.status != StatusCode::OK {
+        return status(state.status);
+    }
+    if request
+        .headers()
+        .get(header::IF_NONE_MATCH)
+        .is_some_and(|etag| etag == state.etag.as_str())
+    {
+        return status(StatusCode::NOT_MODIFIED);
+    }
+
+    let mut response = Response::new(Body::from(state.schema.clone()));
+    response
+        .headers_mut()
+        .insert(header::ETAG, HeaderValue::from_str(&state.etag).unwrap());
+    response
+}
+
+fn status(status: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = status;
+    response
+}